@@ -0,0 +1,105 @@
+// Compares the row based blits against drawing the same thing a pixel at a time through draw(),
+// which is how fill_rect(), clear() and draw_sprite() used to work.
+// Run with `cargo bench --bench blit`.
+
+use std::time::{Duration, Instant};
+
+use olc_pge as olc;
+
+const SCREEN_W: i32 = 320;
+const SCREEN_H: i32 = 240;
+const TILE: i32 = 16;
+const RUN_TIME: Duration = Duration::from_millis(500);
+
+struct Bench;
+
+impl olc::PGEApplication for Bench {
+    const APP_NAME: &'static str = "Blit Benchmark";
+    fn on_user_update(&mut self, _pge: &mut olc::PixelGameEngine, _elapsed_time: f32) -> bool { true }
+}
+
+// average time per call, after a warm up
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    for _ in 0..10 {
+        f();
+    }
+    let start = Instant::now();
+    let mut runs = 0;
+    while start.elapsed() < RUN_TIME {
+        f();
+        runs += 1;
+    }
+    start.elapsed() / runs
+}
+
+fn report(name: &str, per_pixel: Duration, blit: Duration) {
+    println!("{:<24} per pixel {:>10.3?}   blit {:>10.3?}   {:>6.1}x",
+        name, per_pixel, blit, per_pixel.as_secs_f64() / blit.as_secs_f64());
+}
+
+fn main() {
+    let mut context = olc::PixelGameEngine::construct_headless(Bench, SCREEN_W as usize, SCREEN_H as usize, Some(0), vec![]);
+    let pge = context.engine_mut();
+
+    let mut tile = olc::Sprite::new(TILE as u32, TILE as u32);
+    for y in 0..TILE {
+        for x in 0..TILE {
+            let a = if (x + y) % 3 == 0 { 0 } else { 255 };
+            tile.set_pixel(x, y, olc::Pixel::rgba((x * 16) as u8, (y * 16) as u8, 128, a));
+        }
+    }
+    let tile = tile.into_ref();
+
+    for &mode in &[olc::PixelMode::Normal, olc::PixelMode::Mask, olc::PixelMode::Alpha] {
+        pge.set_pixel_mode(mode);
+        println!("{:?}", mode);
+
+        let per_pixel = time(|| {
+            for y in 0..SCREEN_H {
+                for x in 0..SCREEN_W {
+                    pge.draw(x, y, olc::DARK_BLUE);
+                }
+            }
+        });
+        let blit = time(|| pge.fill_rect(0, 0, SCREEN_W as u32, SCREEN_H as u32, olc::DARK_BLUE));
+        report("fill_rect 320x240", per_pixel, blit);
+
+        // a screen full of tiles, hanging off the edges by half a tile
+        let per_pixel = time(|| {
+            let sprite = tile.borrow();
+            for ty in 0..=SCREEN_H / TILE {
+                for tx in 0..=SCREEN_W / TILE {
+                    for y in 0..TILE {
+                        for x in 0..TILE {
+                            pge.draw(tx * TILE - TILE / 2 + x, ty * TILE - TILE / 2 + y, sprite.get_pixel(x, y));
+                        }
+                    }
+                }
+            }
+        });
+        let blit = time(|| {
+            for ty in 0..=SCREEN_H / TILE {
+                for tx in 0..=SCREEN_W / TILE {
+                    pge.draw_sprite(tx * TILE - TILE / 2, ty * TILE - TILE / 2, tile.clone());
+                }
+            }
+        });
+        report("draw_sprite 16x16 tiles", per_pixel, blit);
+    }
+
+    pge.set_pixel_mode(olc::PixelMode::Normal);
+    let mut target = olc::Sprite::new(SCREEN_W as u32, SCREEN_H as u32);
+    let per_pixel = time(|| {
+        for y in 0..SCREEN_H {
+            for x in 0..SCREEN_W {
+                target.set_pixel(x, y, olc::BLACK);
+            }
+        }
+    });
+    let blit = time(|| pge.clear(olc::BLACK));
+    report("clear", per_pixel, blit);
+
+    // use the results, so none of the drawing can be optimised away
+    let checksum = |sprite: &olc::Sprite| sprite.get_data().iter().fold(0u32, |sum, &p| sum.wrapping_add(p));
+    println!("checksum {:08x}", checksum(&target) ^ checksum(&pge.get_draw_target().borrow()));
+}
@@ -20,6 +20,6 @@ impl olc::PGEApplication for Example {
     }
 }
 
-fn main() {
-    olc::PixelGameEngine::construct(Example, 256, 240, 4, 4).start();
+fn main() -> Result<(), olc::PgeError> {
+    olc::PixelGameEngine::try_construct(Example, 256, 240, 4, 4)?.start()
 }
\ No newline at end of file
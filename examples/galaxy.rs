@@ -6,7 +6,6 @@ const STAR_COLOURS: [u32; 8] = [
 ];
 
 pub struct Planet {
-    distance: f64,
     diameter: f64,
     foliage: f64,
     minerals: f64,
@@ -20,7 +19,6 @@ pub struct Planet {
 
 impl Default for Planet {
     fn default() -> Self { Self {
-        distance: 0.0,
         diameter: 0.0,
         foliage: 0.0,
         minerals: 0.0,
@@ -58,12 +56,12 @@ impl StarSystem {
             this.star_colour = STAR_COLOURS[this.rand_i32(0, 8) as usize].into();
 
             if generate_full_system {
-                let mut distance_from_star = this.rand_f64(60.0, 200.0);
+                // orbit distances aren't drawn, but they're still rolled so every system comes out the same
+                this.rand_f64(60.0, 200.0);
                 let planets = this.rand_i32(0, 10);
                 for _ in 0..planets {
                     let mut p = Planet::default();
-                    p.distance = distance_from_star;
-                    distance_from_star += this.rand_f64(20.0, 200.0);
+                    this.rand_f64(20.0, 200.0);
                     p.diameter = this.rand_f64(4.0, 20.0);
                     p.temperature = this.rand_f64(-200.0, 300.0);
                     p.foliage = this.rand_f64(0.0, 1.0);
@@ -208,6 +206,6 @@ impl olc::PGEApplication for Galaxy {
     }
 }
 
-fn main() {
-    olc::PixelGameEngine::construct(Galaxy::default(), 512, 480, 2, 2).start();
+fn main() -> Result<(), olc::PgeError> {
+    olc::PixelGameEngine::try_construct(Galaxy::default(), 512, 480, 2, 2)?.start()
 }
\ No newline at end of file
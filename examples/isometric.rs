@@ -1,3 +1,5 @@
+#![allow(clippy::identity_op, clippy::erasing_op)]
+
 use olc_pge as olc;
 
 struct IsometricDemo {
@@ -51,11 +53,10 @@ impl olc::PGEApplication for IsometricDemo {
         if col == olc::GREEN { selected += olc::Vi2d::new(0, 1); }
         if col == olc::YELLOW { selected += olc::Vi2d::new(1, 0); }
 
-        if pge.get_mouse(0).pressed {
-            if selected.x >= 0 && selected.x < self.world_size.x && selected.y >= 0 && selected.y < self.world_size.y {
-                let index = selected.y * self.world_size.x + selected.x;
-                self.world[index as usize] = (self.world[index as usize] + 1) % 6;
-            }
+        let in_world = selected.x >= 0 && selected.x < self.world_size.x && selected.y >= 0 && selected.y < self.world_size.y;
+        if pge.get_mouse(0).pressed && in_world {
+            let index = selected.y * self.world_size.x + selected.x;
+            self.world[index as usize] = (self.world[index as usize] + 1) % 6;
         }
 
         let to_screen = |x: i32, y: i32| -> olc::Vi2d {
@@ -105,7 +106,7 @@ impl olc::PGEApplication for IsometricDemo {
     }
 }
 
-fn main() {
+fn main() -> Result<(), olc::PgeError> {
     let app = IsometricDemo::default();
-    olc::PixelGameEngine::construct(app, 512, 480, 2, 2).start();
+    olc::PixelGameEngine::try_construct(app, 512, 480, 2, 2)?.start()
 }
\ No newline at end of file
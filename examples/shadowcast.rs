@@ -5,23 +5,13 @@ struct Edge {
     ex: f32, ey: f32  // end coordinate
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Default)]
 struct Cell {
     edge_id: [usize; Direction::COUNT],
     edge_exist: [bool; Direction::COUNT],
     exist: bool
 }
 
-impl Default for Cell {
-    fn default() -> Self {
-        Self {
-            edge_id: [0, 0, 0, 0],
-            edge_exist: [false, false, false, false],
-            exist: false
-        }
-    }
-}
-
 enum Direction {
     North, South, East, West
 }
@@ -229,7 +219,7 @@ impl ShadowCasting2D {
                     rdx = radius * ang.cos();
                     rdy = radius * ang.sin();
 
-                    let mut min_t1 = std::f32::INFINITY;
+                    let mut min_t1 = f32::INFINITY;
                     let (mut min_px, mut min_py, mut min_ang) = (0.0, 0.0, 0.0);
                     let mut valid = false;
 
@@ -245,7 +235,7 @@ impl ShadowCasting2D {
                             let t1 = (e.sx + sdx * t2 - ox) / rdx;
 
                             // if intersect point exists along ray, and along line segment, then intersect is valid
-                            if t1 > 0.0 && t2 >= 0.0 && t2 <= 1.0 {
+                            if t1 > 0.0 && (0.0..=1.0).contains(&t2) {
                                 // check if this intersect point is closest to source
                                 // if it is, then store this point and refect others
                                 if t1 < min_t1 {
@@ -333,7 +323,7 @@ impl olc::PGEApplication for ShadowCasting2D {
                 }
             }
             if unique {
-                new_points.push(t1.clone());
+                new_points.push(*t1);
             }
         }
         self.visibility_polygon_points = new_points;
@@ -358,28 +348,9 @@ impl olc::PGEApplication for ShadowCasting2D {
             // clear offscreen buffer for rays
             pge.clear(olc::BLANK);
 
-            // draw each triangle in fan
-            for i in 0 .. self.visibility_polygon_points.len() - 1 {
-                pge.fill_triangle(
-                    source_x, source_y,
-                    self.visibility_polygon_points[i].1 as i32, self.visibility_polygon_points[i].2 as i32,
-                    self.visibility_polygon_points[i + 1].1 as i32, self.visibility_polygon_points[i + 1].2 as i32,
-                    olc::WHITE
-                );
-            }
-
-            // Fan will have one open edge, so draw last point of fan to first
-            pge.fill_triangle(
-                source_x, source_y,
-
-                self.visibility_polygon_points[self.visibility_polygon_points.len() - 1].1 as i32,
-                self.visibility_polygon_points[self.visibility_polygon_points.len() - 1].2 as i32,
-
-                self.visibility_polygon_points[0].1 as i32,
-                self.visibility_polygon_points[0].2 as i32,
-
-                olc::WHITE
-            );
+            // the points go round the source, so together they outline the visibility polygon
+            let polygon: Vec<olc::Vf2d> = self.visibility_polygon_points.iter().map(|p| olc::Vf2d::new(p.1, p.2)).collect();
+            pge.fill_polygon_aa(&polygon, olc::WHITE);
 
             pge.set_draw_target(None);
 
@@ -387,13 +358,18 @@ impl olc::PGEApplication for ShadowCasting2D {
             let buff_light_tex = self.buff_light_tex.borrow();
             let buff_light_ray = self.buff_light_ray.borrow();
 
+            // the ray buffer holds how much of each pixel is lit, which softens the shadow edges
+            pge.set_pixel_mode(olc::PixelMode::Alpha);
             for x in 0..pge.screen_width() as i32 {
                 for y in 0..pge.screen_height() as i32 {
-                    if buff_light_ray.get_pixel(x, y).r > 0 {
-                        pge.draw(x, y, buff_light_tex.get_pixel(x, y));
+                    let coverage = buff_light_ray.get_pixel(x, y).r;
+                    if coverage > 0 {
+                        let p = buff_light_tex.get_pixel(x, y);
+                        pge.draw(x, y, olc::Pixel::rgba(p.r, p.g, p.b, coverage));
                     }
                 }
             }
+            pge.set_pixel_mode(olc::PixelMode::Normal);
         }
 
         let block_width = block_width as i32;
@@ -418,7 +394,7 @@ impl olc::PGEApplication for ShadowCasting2D {
     }
 }
 
-fn main() {
+fn main() -> Result<(), olc::PgeError> {
     let app = ShadowCasting2D::new(40, 30);
-    olc::PixelGameEngine::construct(app, 640, 480, 2, 2).start();
+    olc::PixelGameEngine::try_construct(app, 640, 480, 2, 2)?.start()
 }
\ No newline at end of file
@@ -141,7 +141,7 @@ impl olc::PGEApplication for PathFindingFlowFields {
 
         let mut no_path = false;
 
-        while !(loc_x == self.end_x && loc_y == self.end_y) && !no_path {
+        while (loc_x != self.end_x || loc_y != self.end_y) && !no_path {
             let mut neighbors = Vec::new();
 
             // 4 way connectivity
@@ -262,7 +262,7 @@ impl olc::PGEApplication for PathFindingFlowFields {
     }
 }
 
-fn main() {
+fn main() -> Result<(), olc::PgeError> {
     let app = PathFindingFlowFields::default();
-    olc::PixelGameEngine::construct(app, 512, 480, 2, 2).start();
+    olc::PixelGameEngine::try_construct(app, 512, 480, 2, 2)?.start()
 }
\ No newline at end of file
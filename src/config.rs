@@ -0,0 +1,78 @@
+use crate::pixel::{Pixel, PixelMode, BLANK};
+
+#[derive(Debug, Clone)]
+pub struct EngineConfig {
+    pub(crate) screen_w: usize,
+    pub(crate) screen_h: usize,
+    pub(crate) pixel_w: usize,
+    pub(crate) pixel_h: usize,
+
+    pub(crate) title: Option<String>,
+    pub(crate) show_fps: bool,
+
+    pub(crate) borderless: bool,
+    pub(crate) topmost: bool,
+    pub(crate) transparency: bool,
+    pub(crate) resizable: bool,
+    pub(crate) integer_scaling: bool,
+    pub(crate) fullscreen: bool,
+    pub(crate) frame_cap: Option<f32>,
+    pub(crate) vsync: bool,
+    pub(crate) fixed_step: f32,
+    pub(crate) max_fixed_steps: u32,
+
+    pub(crate) pixel_mode: PixelMode,
+    pub(crate) clear_colour: Pixel
+}
+
+impl EngineConfig {
+    pub fn new(width: usize, height: usize, pixel_width: usize, pixel_height: usize) -> Self {
+        Self {
+            screen_w: width,
+            screen_h: height,
+            pixel_w: pixel_width,
+            pixel_h: pixel_height,
+
+            title: None,
+            show_fps: true,
+
+            borderless: false,
+            topmost: false,
+            transparency: false,
+            resizable: false,
+            integer_scaling: true,
+            fullscreen: false,
+            frame_cap: None,
+            vsync: false,
+            fixed_step: 1.0 / 60.0,
+            max_fixed_steps: 5,
+
+            pixel_mode: PixelMode::Normal,
+            clear_colour: BLANK
+        }
+    }
+
+    pub fn title(mut self, title: &str) -> Self { self.title = Some(title.to_string()); self }
+    pub fn show_fps(mut self, show_fps: bool) -> Self { self.show_fps = show_fps; self }
+
+    pub fn borderless(mut self, borderless: bool) -> Self { self.borderless = borderless; self }
+    pub fn topmost(mut self, topmost: bool) -> Self { self.topmost = topmost; self }
+    pub fn transparency(mut self, transparency: bool) -> Self { self.transparency = transparency; self }
+    pub fn resizable(mut self, resizable: bool) -> Self { self.resizable = resizable; self }
+    pub fn integer_scaling(mut self, integer_scaling: bool) -> Self { self.integer_scaling = integer_scaling; self }
+    pub fn fullscreen(mut self, fullscreen: bool) -> Self { self.fullscreen = fullscreen; self }
+    pub fn frame_cap(mut self, fps: Option<f32>) -> Self { self.frame_cap = fps; self }
+    pub fn vsync(mut self, vsync: bool) -> Self { self.vsync = vsync; self }
+    pub fn fixed_step(mut self, step: f32) -> Self { self.fixed_step = step; self }
+    pub fn max_fixed_steps(mut self, steps: u32) -> Self { self.max_fixed_steps = steps; self }
+
+    pub fn pixel_mode(mut self, mode: PixelMode) -> Self { self.pixel_mode = mode; self }
+    pub fn clear_colour(mut self, colour: Pixel) -> Self { self.clear_colour = colour; self }
+
+    pub fn window_width(&self) -> usize { self.screen_w * self.pixel_w }
+    pub fn window_height(&self) -> usize { self.screen_h * self.pixel_h }
+    pub fn is_borderless(&self) -> bool { self.borderless }
+    pub fn is_topmost(&self) -> bool { self.topmost }
+    pub fn is_transparent(&self) -> bool { self.transparency }
+    pub fn is_resizable(&self) -> bool { self.resizable }
+}
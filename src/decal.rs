@@ -0,0 +1,110 @@
+use crate::pixel::{Pixel, WHITE, blend_over};
+use crate::sprite::{Sprite, SpriteRef};
+use crate::vector::Vf2d;
+
+// Decals don't have a GPU to live on here, so a decal is just a handle to a sprite.
+// Changes made to the sprite show up the next time the decal is drawn.
+#[derive(Debug, Clone)]
+pub struct Decal {
+    sprite: SpriteRef
+}
+
+impl Decal {
+    pub fn new(sprite: SpriteRef) -> Self { Self { sprite } }
+
+    pub fn get_sprite(&self) -> SpriteRef { self.sprite.clone() }
+    pub fn width(&self) -> u32 { self.sprite.borrow().width() }
+    pub fn height(&self) -> u32 { self.sprite.borrow().height() }
+}
+
+// corners go clockwise from the top left of the source rectangle
+#[derive(Debug, Clone)]
+pub(crate) struct DecalInstance {
+    pub(crate) decal: Decal,
+    pub(crate) pos: [Vf2d; 4],
+    pub(crate) source_pos: Vf2d,
+    pub(crate) source_size: Vf2d,
+    pub(crate) tint: Pixel
+}
+
+impl DecalInstance {
+    pub(crate) fn draw(&self, target: &mut Sprite) {
+        let sprite = self.decal.sprite.borrow();
+        let inverse = match square_to_quad(&self.pos).and_then(invert) {
+            Some(m) => m,
+            None => return
+        };
+
+        let min_x = self.pos.iter().map(|p| p.x).fold(f32::INFINITY, f32::min).floor().max(0.0) as i32;
+        let min_y = self.pos.iter().map(|p| p.y).fold(f32::INFINITY, f32::min).floor().max(0.0) as i32;
+        let max_x = self.pos.iter().map(|p| p.x).fold(f32::NEG_INFINITY, f32::max).ceil().min(target.width() as f32) as i32;
+        let max_y = self.pos.iter().map(|p| p.y).fold(f32::NEG_INFINITY, f32::max).ceil().min(target.height() as f32) as i32;
+
+        // every pixel centre in the bounding box is mapped back onto the source rectangle
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                let w = inverse[6] * px + inverse[7] * py + inverse[8];
+                let s = (inverse[0] * px + inverse[1] * py + inverse[2]) / w;
+                let t = (inverse[3] * px + inverse[4] * py + inverse[5]) / w;
+                if !(0.0..1.0).contains(&s) || !(0.0..1.0).contains(&t) {
+                    continue
+                }
+
+                let sx = (self.source_pos.x + s * self.source_size.x).floor() as i32;
+                let sy = (self.source_pos.y + t * self.source_size.y).floor() as i32;
+                let p = tint(sprite.get_pixel(sx, sy), self.tint);
+                if p.a > 0 {
+                    target.set_pixel(x, y, blend_over(p, target.get_pixel(x, y), 1.0));
+                }
+            }
+        }
+    }
+}
+
+fn tint(p: Pixel, tint: Pixel) -> Pixel {
+    if tint == WHITE {
+        return p
+    }
+    let mul = |a: u8, b: u8| ((a as u32 * b as u32 + 127) / 255) as u8;
+    Pixel::rgba(mul(p.r, tint.r), mul(p.g, tint.g), mul(p.b, tint.b), mul(p.a, tint.a))
+}
+
+// Projective mapping from the unit square onto a quad (Heckbert, "Fundamentals of Texture Mapping").
+// Returned as a row major 3x3 matrix, or None if the quad has no area.
+fn square_to_quad(q: &[Vf2d; 4]) -> Option<[f32; 9]> {
+    let sx = q[0].x - q[1].x + q[2].x - q[3].x;
+    let sy = q[0].y - q[1].y + q[2].y - q[3].y;
+    let (dx1, dx2) = (q[1].x - q[2].x, q[3].x - q[2].x);
+    let (dy1, dy2) = (q[1].y - q[2].y, q[3].y - q[2].y);
+
+    let (g, h) = if sx.abs() < f32::EPSILON && sy.abs() < f32::EPSILON {
+        (0.0, 0.0)
+    }
+    else {
+        let det = dx1 * dy2 - dx2 * dy1;
+        if det.abs() < f32::EPSILON {
+            return None
+        }
+        ((sx * dy2 - dx2 * sy) / det, (dx1 * sy - sx * dy1) / det)
+    };
+
+    Some([
+        q[1].x - q[0].x + g * q[1].x, q[3].x - q[0].x + h * q[3].x, q[0].x,
+        q[1].y - q[0].y + g * q[1].y, q[3].y - q[0].y + h * q[3].y, q[0].y,
+        g, h, 1.0
+    ])
+}
+
+fn invert(m: [f32; 9]) -> Option<[f32; 9]> {
+    let adj = [
+        m[4] * m[8] - m[5] * m[7], m[2] * m[7] - m[1] * m[8], m[1] * m[5] - m[2] * m[4],
+        m[5] * m[6] - m[3] * m[8], m[0] * m[8] - m[2] * m[6], m[2] * m[3] - m[0] * m[5],
+        m[3] * m[7] - m[4] * m[6], m[1] * m[6] - m[0] * m[7], m[0] * m[4] - m[1] * m[3]
+    ];
+    let det = m[0] * adj[0] + m[1] * adj[3] + m[2] * adj[6];
+    if det.abs() < f32::EPSILON {
+        return None
+    }
+    Some(adj)
+}
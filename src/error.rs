@@ -0,0 +1,42 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum PgeError {
+    WindowCreation(Box<dyn Error + Send + Sync>),
+    ImageLoad(image::ImageError),
+    ImageSave(image::ImageError),
+    Backend(Box<dyn Error + Send + Sync>),
+    ResourcePack(std::io::Error),
+    UserAbort
+}
+
+impl fmt::Display for PgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgeError::WindowCreation(e) => write!(f, "failed to create window: {}", e),
+            PgeError::ImageLoad(e) => write!(f, "failed to load image: {}", e),
+            PgeError::ImageSave(e) => write!(f, "failed to save image: {}", e),
+            PgeError::Backend(e) => write!(f, "platform error: {}", e),
+            PgeError::ResourcePack(e) => write!(f, "resource pack error: {}", e),
+            PgeError::UserAbort => write!(f, "aborted by the application")
+        }
+    }
+}
+
+impl Error for PgeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PgeError::WindowCreation(e) => Some(e.as_ref()),
+            PgeError::ImageLoad(e) => Some(e),
+            PgeError::ImageSave(e) => Some(e),
+            PgeError::Backend(e) => Some(e.as_ref()),
+            PgeError::ResourcePack(e) => Some(e),
+            PgeError::UserAbort => None
+        }
+    }
+}
+
+impl From<image::ImageError> for PgeError {
+    fn from(e: image::ImageError) -> Self { PgeError::ImageLoad(e) }
+}
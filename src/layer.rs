@@ -0,0 +1,73 @@
+use crate::decal::{Decal, DecalInstance};
+use crate::pixel::{Pixel, BLACK, WHITE};
+use crate::sprite::{Sprite, SpriteRef};
+use crate::vector::Vf2d;
+
+#[derive(Debug)]
+pub(crate) struct Layer {
+    pub(crate) draw_target: SpriteRef,
+    pub(crate) enabled: bool,
+    pub(crate) offset: Vf2d,
+    pub(crate) scale: Vf2d,
+    pub(crate) tint: Pixel,
+    pub(crate) decal_instances: Vec<DecalInstance>
+}
+
+impl Layer {
+    pub(crate) fn new(draw_target: SpriteRef) -> Self {
+        Self {
+            draw_target,
+            enabled: false,
+            offset: Vf2d::new(0.0, 0.0),
+            scale: Vf2d::new(1.0, 1.0),
+            tint: WHITE,
+            decal_instances: vec![]
+        }
+    }
+
+    pub(crate) fn is_identity(&self) -> bool {
+        self.offset == Vf2d::new(0.0, 0.0) && self.scale == Vf2d::new(1.0, 1.0) && self.tint == WHITE
+    }
+
+    // blends the layer over whatever is already in target, then its decals on top
+    pub(crate) fn draw(&self, target: &mut Sprite, copy: bool) {
+        if copy {
+            target.copy_from(&self.draw_target.borrow());
+        }
+        else {
+            let sprite = self.draw_target.borrow();
+            let size = Vf2d::new(sprite.width() as f32, sprite.height() as f32);
+            let (w, h) = (size.x * self.scale.x, size.y * self.scale.y);
+            let o = self.offset;
+            drop(sprite);
+
+            DecalInstance {
+                decal: Decal::new(self.draw_target.clone()),
+                pos: [o, o + (w, 0.0), o + (w, h), o + (0.0, h)],
+                source_pos: Vf2d::new(0.0, 0.0),
+                source_size: size,
+                tint: self.tint
+            }.draw(target);
+        }
+
+        for instance in &self.decal_instances {
+            instance.draw(target);
+        }
+    }
+}
+
+// Layers are drawn from the last one down to layer 0.
+// The bottom layer is copied straight in when it can be, which is the same as having no layers at all.
+pub(crate) fn composite(layers: &[Layer], target: &mut Sprite) {
+    let mut first = true;
+    for layer in layers.iter().rev().filter(|layer| layer.enabled) {
+        if first && !layer.is_identity() {
+            target.clear(BLACK);
+        }
+        layer.draw(target, first && layer.is_identity());
+        first = false;
+    }
+    if first {
+        target.clear(BLACK);
+    }
+}
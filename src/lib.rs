@@ -1,20 +1,42 @@
-use std::time::Instant;
+#![allow(clippy::too_many_arguments)]
 
+use std::time::{Duration, Instant};
+
+pub mod config;
+pub mod decal;
+pub mod error;
+mod layer;
 pub mod pixel;
+pub mod platform;
+pub mod recorder;
+pub mod resource_pack;
 pub mod sprite;
 pub mod vector;
 
+pub use config::EngineConfig;
+pub use decal::Decal;
+pub use error::PgeError;
 pub use pixel::*;
+pub use platform::{Platform, PlatformResult};
+pub use platform::headless::{HeadlessPlatform, InputFrame};
+pub use recorder::{Recorder, RecordFormat};
+pub use resource_pack::ResourcePack;
+#[cfg(feature = "minifb")]
+pub use platform::minifb::MinifbPlatform;
 pub use sprite::*;
 pub use vector::*;
 
+pub use image::RgbaImage;
+
 mod font_data;
 use font_data::FONT_DATA;
+use decal::DecalInstance;
+use layer::Layer;
+use recorder::RecordingRequest;
 
-#[derive(PartialEq)]
-pub enum RCode {
-    Fail, Ok, NoFile
-}
+// sleeping is only accurate to a millisecond or two, so the end of each frame is spun out
+const SPIN_TIME: Duration = Duration::from_millis(2);
+const DEFAULT_REFRESH_RATE: f32 = 60.0;
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum Key {
@@ -38,46 +60,18 @@ pub enum Key {
     Count = 109
 }
 
-const KEYMAP: [Key; minifb::Key::Count as usize] = [
-    Key::K0, Key::K1, Key::K2, Key::K3, Key::K4, Key::K5, Key::K6, Key::K7, Key::K8, Key::K9,
-    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K, Key::L,
-    Key::M, Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X,
-    Key::Y, Key::Z,
-    Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6,
-    Key::F7, Key::F8, Key::F9, Key::F10, Key::F11, Key::F12,
-    Key::None, Key::None, Key::None, // F13, F14, F15
-    Key::Down, Key::Left, Key::Right, Key::Up,
-    Key::Apostrophe, Key::BackQuote, Key::BackSlash, Key::Comma,
-    Key::Equal, Key::LeftBracket, Key::Minus, Key::Period,
-    Key::RightBracket, Key::Semicolon, Key::Slash,
-    Key::Back, Key::Delete, Key::End, Key::Return, Key::Escape,
-    Key::Home, Key::Insert, Key::Menu,
-    Key::PageDown, Key::PageUp, Key::Pause, Key::Space, Key::Tab,
-    Key::NumLock, Key::CapsLock, Key::ScrollLock,
-    Key::LeftShift, Key::RightShift, Key::LeftControl, Key::RightControl,
-    Key::NumPad0, Key::NumPad1, Key::NumPad2, Key::NumPad3, Key::NumPad4,
-    Key::NumPad5, Key::NumPad6, Key::NumPad7, Key::NumPad8, Key::NumPad9,
-    Key::NumPadDecimal, Key::NumPadDiv, Key::NumPadMul, Key::NumPadSub, Key::NumPadAdd,
-    Key::NumPadEnter, Key::LeftAlt, Key::RightAlt,
-    Key::LeftSystem, Key::RightSystem,
-    Key::None // Unknown
-];
-
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Default)]
 pub struct HWButton {
     pub pressed: bool,
     pub released: bool,
     pub held: bool
 }
 
-impl Default for HWButton {
-    fn default() -> Self { Self { pressed: false, released: false, held: false } }
-}
-
 pub trait PGEApplication {
     const APP_NAME: &'static str;
     fn on_user_create(&mut self, _pge: &mut PixelGameEngine) -> bool { true }
     fn on_user_update(&mut self, pge: &mut PixelGameEngine, elapsed_time: f32) -> bool;
+    fn on_fixed_update(&mut self, _pge: &mut PixelGameEngine, _fixed_time: f32) -> bool { true }
     fn on_user_destroy(&mut self) -> bool { true }
 }
 
@@ -101,14 +95,45 @@ pub struct PixelGameEngine {
     draw_target: SpriteRef,
 
     pixel_mode: PixelMode,
+    custom_pixel_mode: Option<CustomPixelMode>,
     blend_factor: f32,
-
-    window: minifb::Window
+    clip_stack: Vec<(i32, i32, i32, i32)>,
+
+    layers: Vec<Layer>,
+    target_layer: usize,
+    font_decal: Decal,
+    composite: Sprite,
+
+    resizable: bool,
+    fullscreen: bool,
+    integer_scaling: bool,
+    viewport_pos: Vi2d,
+    viewport_size: Vi2d,
+    sub_pixel_offset: Vf2d,
+    present_buffer: Vec<u32>,
+
+    recording: bool,
+    recording_request: Option<RecordingRequest>,
+    recording_error: Option<PgeError>,
+
+    title: String,
+    show_fps: bool,
+
+    frame_cap: Option<f32>,
+    vsync: bool,
+    native_vsync: bool,
+    refresh_rate: f32,
+
+    fixed_step: f32,
+    max_fixed_steps: u32,
+    fixed_alpha: f32,
+
+    platform: Box<dyn Platform>
 }
 
 impl PixelGameEngine {
     // Hardware Interfaces
-    pub fn is_focused(&mut self) -> bool { self.window.is_active() }
+    pub fn is_focused(&mut self) -> bool { self.platform.is_focused() }
     pub fn get_key(&self, k: Key) -> HWButton { self.keyboard_state[k as usize] }
     pub fn get_mouse(&self, button: usize) -> HWButton { self.mouse_state[button] }
     pub fn get_mouse_x(&self) -> i32 { self.mouse_pos_x }
@@ -121,18 +146,95 @@ impl PixelGameEngine {
     pub fn get_draw_target_width(&self) -> u32 { self.draw_target.borrow().width() }
     pub fn get_draw_target_height(&self) -> u32 { self.draw_target.borrow().height() }
     pub fn get_draw_target(&self) -> SpriteRef { self.draw_target.clone() }
+    pub fn get_viewport_pos(&self) -> Vi2d { self.viewport_pos }
+    pub fn get_viewport_size(&self) -> Vi2d { self.viewport_size }
+
+    pub fn set_resizable(&mut self, resizable: bool) -> Result<(), PgeError> {
+        self.platform.set_resizable(resizable).map_err(PgeError::Backend)?;
+        self.resizable = resizable;
+        Ok(())
+    }
+    pub fn is_resizable(&self) -> bool { self.resizable }
+    pub fn set_fullscreen(&mut self, fullscreen: bool) -> Result<(), PgeError> {
+        self.platform.set_fullscreen(fullscreen).map_err(PgeError::Backend)?;
+        self.fullscreen = fullscreen;
+        self._update_viewport();
+        Ok(())
+    }
+    pub fn is_fullscreen(&self) -> bool { self.fullscreen }
+
+    pub fn set_frame_cap(&mut self, fps: Option<f32>) { self.frame_cap = fps.filter(|fps| *fps > 0.0); }
+    pub fn get_frame_cap(&self) -> Option<f32> { self.frame_cap }
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.native_vsync = self.platform.set_vsync(vsync);
+        if vsync && !self.native_vsync {
+            self.refresh_rate = self.platform.get_refresh_rate().unwrap_or(DEFAULT_REFRESH_RATE);
+        }
+        self.vsync = vsync;
+    }
+    pub fn is_vsync(&self) -> bool { self.vsync }
+
+    pub fn set_fixed_step(&mut self, step: f32) { self.fixed_step = step.max(f32::EPSILON); }
+    pub fn get_fixed_step(&self) -> f32 { self.fixed_step }
+    pub fn set_max_fixed_steps(&mut self, steps: u32) { self.max_fixed_steps = steps; }
+    pub fn get_max_fixed_steps(&self) -> u32 { self.max_fixed_steps }
+    // how far between the last and next fixed update this frame is, from 0 to 1
+    pub fn get_fixed_alpha(&self) -> f32 { self.fixed_alpha }
+    pub fn set_integer_scaling(&mut self, integer_scaling: bool) { self.integer_scaling = integer_scaling; }
 
     // Draw Routines
     pub fn set_draw_target(&mut self, target: Option<SpriteRef>) {
         match target {
             Some(target) => self.draw_target = target,
-            None => self.draw_target = self.default_draw_target.clone()
+            None => {
+                self.draw_target = self.default_draw_target.clone();
+                self.target_layer = 0;
+            }
+        }
+    }
+    // decals are drawn over whichever layer was targeted last
+    pub fn set_draw_target_layer(&mut self, layer: usize) {
+        if let Some(l) = self.layers.get(layer) {
+            self.draw_target = l.draw_target.clone();
+            self.target_layer = layer;
         }
     }
+
+    // Layer 0 is the screen. Layers are drawn from the last one created down to layer 0, so layer 0 is always on top.
+    pub fn create_layer(&mut self) -> usize {
+        let sprite = Sprite::new(self.screen_w as u32, self.screen_h as u32);
+        self.layers.push(Layer::new(sprite.into_ref()));
+        self.layers.len() - 1
+    }
+    pub fn get_layer_count(&self) -> usize { self.layers.len() }
+    pub fn enable_layer(&mut self, layer: usize, enabled: bool) { if let Some(l) = self.layers.get_mut(layer) { l.enabled = enabled; } }
+    pub fn set_layer_offset(&mut self, layer: usize, offset: Vf2d) { if let Some(l) = self.layers.get_mut(layer) { l.offset = offset; } }
+    pub fn set_layer_scale(&mut self, layer: usize, scale: Vf2d) { if let Some(l) = self.layers.get_mut(layer) { l.scale = scale; } }
+    pub fn set_layer_tint(&mut self, layer: usize, tint: Pixel) { if let Some(l) = self.layers.get_mut(layer) { l.tint = tint; } }
     pub fn set_pixel_mode(&mut self, mode: PixelMode) { self.pixel_mode = mode; }
+    // set_pixel_mode(PixelMode::Custom) afterwards goes back to the last function given here
+    pub fn set_pixel_mode_custom<F>(&mut self, mode: F) where F: Fn(i32, i32, Pixel, Pixel) -> Pixel + 'static {
+        self.custom_pixel_mode = Some(CustomPixelMode(Box::new(mode)));
+        self.pixel_mode = PixelMode::Custom;
+    }
     pub fn get_pixel_mode(&self) -> PixelMode { self.pixel_mode }
     pub fn set_pixel_blend(&mut self, blend: f32) { self.blend_factor = blend; }
-    pub fn set_sub_pixel_offset(&mut self, _ox: f32, _oy: f32) { unimplemented!() }
+    // in screen pixels, positive moves the frame right and down
+    pub fn set_sub_pixel_offset(&mut self, ox: f32, oy: f32) { self.sub_pixel_offset = Vf2d::new(ox, oy); }
+    pub fn get_sub_pixel_offset(&self) -> Vf2d { self.sub_pixel_offset }
+
+    // Clip rectangles are in draw target pixels. Each one is cut down to fit inside the one before it,
+    // and popping goes back to the previous one.
+    pub fn push_clip_rect(&mut self, x: i32, y: i32, w: u32, h: u32) {
+        let (x1, y1) = ((x as i64 + w as i64).min(i32::MAX as i64) as i32, (y as i64 + h as i64).min(i32::MAX as i64) as i32);
+        let clip = match self.clip_stack.last() {
+            Some(&(cx0, cy0, cx1, cy1)) => (x.max(cx0), y.max(cy0), x1.min(cx1), y1.min(cy1)),
+            None => (x, y, x1, y1)
+        };
+        self.clip_stack.push(clip);
+    }
+    pub fn push_clip_rect_v(&mut self, pos: Vi2d, size: Vi2d) { self.push_clip_rect(pos.x, pos.y, size.x.max(0) as u32, size.y.max(0) as u32); }
+    pub fn pop_clip_rect(&mut self) { self.clip_stack.pop(); }
 
     pub fn draw(&mut self, x: i32, y: i32, p: Pixel) -> bool { self._draw(x, y, p) }
     pub fn draw_v(&mut self, pos: Vi2d, p: Pixel) -> bool { self._draw(pos.x, pos.y, p) }
@@ -164,169 +266,304 @@ impl PixelGameEngine {
     pub fn fill_triangle(&mut self, x1: i32, y1: i32, x2: i32, y2: i32, x3: i32, y3: i32, p: Pixel) { self._fill_triangle(x1, y1, x2, y2, x3, y3, p); }
     pub fn fill_triangle_v(&mut self, pos1: Vi2d, pos2: Vi2d, pos3: Vi2d, p: Pixel) { self._fill_triangle(pos1.x, pos1.y, pos2.x, pos2.y, pos3.x, pos3.y, p); }
 
-    pub fn draw_sprite(&mut self, x: i32, y: i32, sprite: SpriteRef) { self._draw_sprite(x, y, sprite, 1); }
-    pub fn draw_sprite_scaled(&mut self, x: i32, y: i32, sprite: SpriteRef, scale: u32) { self._draw_sprite(x, y, sprite, scale); }
+    // Anti-aliased shapes take float coordinates, with pixel centres on whole numbers for lines and circles.
+    // Edge coverage goes out through the alpha channel, so Normal and Mask draw as Alpha here.
+    pub fn draw_line_aa(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, p: Pixel) { self._draw_line_aa(x1, y1, x2, y2, p); }
+    pub fn draw_line_aa_v(&mut self, pos1: Vf2d, pos2: Vf2d, p: Pixel) { self._draw_line_aa(pos1.x, pos1.y, pos2.x, pos2.y, p); }
+
+    pub fn draw_circle_aa(&mut self, x: f32, y: f32, radius: f32, p: Pixel) { self._draw_circle_aa(x, y, radius, p); }
+    pub fn draw_circle_aa_v(&mut self, pos: Vf2d, radius: f32, p: Pixel) { self._draw_circle_aa(pos.x, pos.y, radius, p); }
+
+    // even-odd fill, so self intersecting polygons get holes
+    pub fn fill_polygon_aa(&mut self, points: &[Vf2d], p: Pixel) { self._fill_polygon_aa(points, p); }
+
+    pub fn draw_sprite(&mut self, x: i32, y: i32, sprite: SpriteRef) { self._draw_sprite(x, y, sprite, 1, SpriteFlip::None); }
+    pub fn draw_sprite_scaled(&mut self, x: i32, y: i32, sprite: SpriteRef, scale: u32) { self._draw_sprite(x, y, sprite, scale, SpriteFlip::None); }
+    pub fn draw_sprite_flip(&mut self, x: i32, y: i32, sprite: SpriteRef, scale: u32, flip: SpriteFlip) { self._draw_sprite(x, y, sprite, scale, flip); }
 
     pub fn draw_sprite_v(&mut self, pos: Vi2d, sprite: SpriteRef) { self.draw_sprite_scaled(pos.x, pos.y, sprite, 1); }
     pub fn draw_sprite_scaled_v(&mut self, pos: Vi2d, sprite: SpriteRef, scale: u32) { self.draw_sprite_scaled(pos.x, pos.y, sprite, scale); }
+    pub fn draw_sprite_flip_v(&mut self, pos: Vi2d, sprite: SpriteRef, scale: u32, flip: SpriteFlip) { self._draw_sprite(pos.x, pos.y, sprite, scale, flip); }
+
+    pub fn draw_partial_sprite(&mut self, x: i32, y: i32, sprite: SpriteRef, ox: i32, oy: i32, w: u32, h: u32) { self._draw_partial_sprite(x, y, sprite, ox, oy, w, h, 1, SpriteFlip::None); }
+    pub fn draw_partial_sprite_scaled(&mut self, x: i32, y: i32, sprite: SpriteRef, ox: i32, oy: i32, w: u32, h: u32, scale: u32) { self._draw_partial_sprite(x, y, sprite, ox, oy, w, h, scale, SpriteFlip::None); }
+    pub fn draw_partial_sprite_flip(&mut self, x: i32, y: i32, sprite: SpriteRef, ox: i32, oy: i32, w: u32, h: u32, scale: u32, flip: SpriteFlip) { self._draw_partial_sprite(x, y, sprite, ox, oy, w, h, scale, flip); }
 
-    pub fn draw_partial_sprite(&mut self, x: i32, y: i32, sprite: SpriteRef, ox: i32, oy: i32, w: u32, h: u32) { self._draw_partial_sprite(x, y, sprite, ox, oy, w, h, 1); }
-    pub fn draw_partial_sprite_scaled(&mut self, x: i32, y: i32, sprite: SpriteRef, ox: i32, oy: i32, w: u32, h: u32, scale: u32) { self._draw_partial_sprite(x, y, sprite, ox, oy, w, h, scale); }
+    pub fn draw_partial_sprite_v(&mut self, pos: Vi2d, sprite: SpriteRef, source_pos: Vi2d, size: Vi2d) { self._draw_partial_sprite(pos.x, pos.y, sprite, source_pos.x, source_pos.y, size.x as u32, size.y as u32, 1, SpriteFlip::None); }
+    pub fn draw_partial_sprite_scaled_v(&mut self, pos: Vi2d, sprite: SpriteRef, source_pos: Vi2d, size: Vi2d, scale: u32) { self._draw_partial_sprite(pos.x, pos.y, sprite, source_pos.x, source_pos.y, size.x as u32, size.y as u32, scale, SpriteFlip::None); }
+    pub fn draw_partial_sprite_flip_v(&mut self, pos: Vi2d, sprite: SpriteRef, source_pos: Vi2d, size: Vi2d, scale: u32, flip: SpriteFlip) { self._draw_partial_sprite(pos.x, pos.y, sprite, source_pos.x, source_pos.y, size.x as u32, size.y as u32, scale, flip); }
 
-    pub fn draw_partial_sprite_v(&mut self, pos: Vi2d, sprite: SpriteRef, source_pos: Vi2d, size: Vi2d) { self._draw_partial_sprite(pos.x, pos.y, sprite, source_pos.x, source_pos.y, size.x as u32, size.y as u32, 1); }
-    pub fn draw_partial_sprite_scaled_v(&mut self, pos: Vi2d, sprite: SpriteRef, source_pos: Vi2d, size: Vi2d, scale: u32) { self._draw_partial_sprite(pos.x, pos.y, sprite, source_pos.x, source_pos.y, size.x as u32, size.y as u32, scale); }
+    // draws the source rectangle of the sprite stretched by scale, which can shrink it too
+    pub fn draw_sprite_ex(&mut self, pos: Vf2d, sprite: SpriteRef, source_pos: Vi2d, source_size: Vi2d, scale: Vf2d, flip: SpriteFlip, filter: SpriteFilter) { self._draw_sprite_ex(pos, sprite, source_pos, source_size, scale, flip, filter); }
 
-    pub fn draw_string(&mut self, x: i32, y: i32, text: &String, col: Pixel) { self._draw_string_scaled(x, y, text, col, 1); }
-    pub fn draw_string_scaled(&mut self, x: i32, y: i32, text: &String, col: Pixel, scale: u32) { self._draw_string_scaled(x, y, text, col, scale); }
+    // origin is in sprite pixels, and ends up at pos
+    pub fn draw_sprite_rotated(&mut self, pos: Vf2d, sprite: SpriteRef, angle: f32, origin: Vf2d, scale: f32, filter: SpriteFilter) { self._draw_sprite_rotated(pos, sprite, angle, origin, scale, filter); }
 
-    pub fn draw_string_v(&mut self, pos: Vi2d, text: &String, col: Pixel) { self._draw_string_scaled(pos.x, pos.y, text, col, 1); }
-    pub fn draw_string_scaled_v(&mut self, pos: Vi2d, text: &String, col: Pixel, scale: u32) { self._draw_string_scaled(pos.x, pos.y, text, col, scale); }
+    pub fn draw_string(&mut self, x: i32, y: i32, text: &str, col: Pixel) { self._draw_string_scaled(x, y, text, col, 1); }
+    pub fn draw_string_scaled(&mut self, x: i32, y: i32, text: &str, col: Pixel, scale: u32) { self._draw_string_scaled(x, y, text, col, scale); }
+
+    pub fn draw_string_v(&mut self, pos: Vi2d, text: &str, col: Pixel) { self._draw_string_scaled(pos.x, pos.y, text, col, 1); }
+    pub fn draw_string_scaled_v(&mut self, pos: Vi2d, text: &str, col: Pixel, scale: u32) { self._draw_string_scaled(pos.x, pos.y, text, col, scale); }
+
+    // Decals are queued up and drawn over the screen once on_user_update() is done
+    pub fn draw_decal(&mut self, pos: Vf2d, decal: &Decal, scale: Vf2d, tint: Pixel) {
+        let size = Vf2d::new(decal.width() as f32, decal.height() as f32);
+        self._draw_partial_decal(pos, decal, Vf2d::new(0.0, 0.0), size, scale, tint);
+    }
+    pub fn draw_partial_decal(&mut self, pos: Vf2d, decal: &Decal, source_pos: Vf2d, source_size: Vf2d, scale: Vf2d, tint: Pixel) { self._draw_partial_decal(pos, decal, source_pos, source_size, scale, tint); }
+    pub fn draw_rotated_decal(&mut self, pos: Vf2d, decal: &Decal, angle: f32, center: Vf2d, scale: Vf2d, tint: Pixel) { self._draw_rotated_decal(pos, decal, angle, center, scale, tint); }
+    // corners go top left, bottom left, bottom right, top right, like the C++ version
+    pub fn draw_warped_decal(&mut self, decal: &Decal, pos: [Vf2d; 4], tint: Pixel) {
+        let size = Vf2d::new(decal.width() as f32, decal.height() as f32);
+        self._queue_decal(decal, [pos[0], pos[3], pos[2], pos[1]], Vf2d::new(0.0, 0.0), size, tint);
+    }
+    pub fn draw_string_decal(&mut self, pos: Vf2d, text: &str, col: Pixel, scale: Vf2d) { self._draw_string_decal(pos, text, col, scale); }
 
     pub fn clear(&mut self, p: Pixel) { self._clear(p); }
-    pub fn set_screen_size(&mut self, _w: usize, _h: usize) { unimplemented!() }
+
+    // what the screen would show if it were presented now, with every layer and this frame's decals in it
+    pub fn capture_screen(&self) -> Sprite {
+        let mut screen = Sprite::new(self.screen_w as u32, self.screen_h as u32);
+        layer::composite(&self.layers, &mut screen);
+        screen
+    }
+
+    // the same as the context's recording functions, but usable from on_user_update()
+    pub fn request_recording(&mut self, recorder: Recorder) { self.recording_request = Some(RecordingRequest::Start(Box::new(recorder))); }
+    pub fn request_stop_recording(&mut self) { self.recording_request = Some(RecordingRequest::Stop); }
+    pub fn is_recording(&self) -> bool {
+        match self.recording_request {
+            Some(RecordingRequest::Start(_)) => true,
+            Some(RecordingRequest::Stop) => false,
+            None => self.recording
+        }
+    }
+    // a recording that fails is stopped and the error kept here, rather than ending the game
+    pub fn take_recording_error(&mut self) -> Option<PgeError> { self.recording_error.take() }
+
+    // scaled screenshots have each screen pixel blown up to pixel_w by pixel_h
+    pub fn save_screenshot(&self, file_name: &str, scaled: bool) -> Result<(), PgeError> {
+        let screen = self.capture_screen();
+        if !scaled || (self.pixel_w == 1 && self.pixel_h == 1) {
+            return screen.save_file(file_name)
+        }
+
+        let (pw, ph) = (self.pixel_w as u32, self.pixel_h as u32);
+        let img = RgbaImage::from_fn(screen.width() * pw, screen.height() * ph, |x, y| {
+            let p = screen.get_pixel((x / pw) as i32, (y / ph) as i32);
+            image::Rgba([p.r, p.g, p.b, p.a])
+        });
+        img.save(file_name).map_err(PgeError::ImageSave)
+    }
+
+    pub fn set_screen_size(&mut self, w: usize, h: usize) -> Result<(), PgeError> {
+        self.platform.set_size(w * self.pixel_w, h * self.pixel_h).map_err(PgeError::Backend)?;
+
+        self.screen_w = w;
+        self.screen_h = h;
+        for layer in &self.layers {
+            *layer.draw_target.borrow_mut() = Sprite::new(w as u32, h as u32);
+        }
+        self.composite = Sprite::new(w as u32, h as u32);
+        self._update_viewport();
+
+        Ok(())
+    }
 
     // implementations
 
     #[inline]
     fn _draw(&mut self, x: i32, y: i32, p: Pixel) -> bool {
+        if let Some(&(x0, y0, x1, y1)) = self.clip_stack.last() {
+            if x < x0 || x >= x1 || y < y0 || y >= y1 {
+                return false
+            }
+        }
         let mut draw_target = self.draw_target.borrow_mut();
         match self.pixel_mode {
             PixelMode::Normal => draw_target.set_pixel(x, y, p),
             PixelMode::Mask => if p.a == 255 { draw_target.set_pixel(x, y, p) } else { false }
             PixelMode::Alpha => {
                 let d = draw_target.get_pixel(x, y);
-                let a = (p.a as f32 / 255.0) * self.blend_factor;
-                let c = 1.0 - a;
-                let r = a * p.r as f32 + c * d.r as f32;
-                let g = a * p.g as f32 + c * d.g as f32;
-                let b = a * p.b as f32 + c * d.b as f32;
-                draw_target.set_pixel(x, y, Pixel::rgb(r as u8, g as u8, b as u8))
+                draw_target.set_pixel(x, y, blend_alpha(p, d, self.blend_factor))
             }
-            PixelMode::Custom => {
-                unimplemented!()
-                //draw_target.set_pixel(x, y, p)
+            PixelMode::Additive => self._blend(&mut draw_target, x, y, p, |s, d| s + d),
+            PixelMode::Multiply => self._blend(&mut draw_target, x, y, p, |s, d| s * d / 255.0),
+            PixelMode::Screen => self._blend(&mut draw_target, x, y, p, |s, d| 255.0 - (255.0 - s) * (255.0 - d) / 255.0),
+            PixelMode::Subtract => self._blend(&mut draw_target, x, y, p, |s, d| d - s),
+            PixelMode::Min => self._blend(&mut draw_target, x, y, p, f32::min),
+            PixelMode::Max => self._blend(&mut draw_target, x, y, p, f32::max),
+            PixelMode::Over => {
+                let d = draw_target.get_pixel(x, y);
+                draw_target.set_pixel(x, y, blend_over(p, d, self.blend_factor))
+            }
+            PixelMode::Custom => match &self.custom_pixel_mode {
+                Some(CustomPixelMode(mode)) => {
+                    let d = draw_target.get_pixel(x, y);
+                    draw_target.set_pixel(x, y, mode(x, y, p, d))
+                }
+                None => draw_target.set_pixel(x, y, p)
             }
         }
     }
 
     #[inline]
-    fn _draw_line_pattern(&mut self, x1: i32, y1: i32, x2: i32, y2: i32, p: Pixel, pattern: u32) {
-        let dx = x2 - x1;
-        let dy = y2 - y1;
-
-        let mut pattern = pattern;
-
-        let mut rol = || -> bool {
-            pattern = pattern.rotate_left(1);
-            pattern & 1 != 0
-        };
-
-        let dx1 = dx.abs();
-        let dy1 = dy.abs();
-
-        let mut px = 2 * dy1 - dx1;
-        let mut py = 2 * dx1 - dy1;
-
-        if dy1 <= dx1 {
-            let (mut x, mut y, xe) =
-                if dx >= 0 { (x1, y1, x2) }
-                else { (x2, y2, x1) };
-
-            if rol() { self.draw(x, y, p); }
+    fn _blend(&self, draw_target: &mut Sprite, x: i32, y: i32, p: Pixel, op: impl Fn(f32, f32) -> f32) -> bool {
+        let d = draw_target.get_pixel(x, y);
+        draw_target.set_pixel(x, y, blend_channels(p, d, self.blend_factor, op))
+    }
 
-            while x < xe {
-                x += 1;
-                if px < 0 {
-                    px += 2 * dy1;
-                }
-                else {
-                    if (dx < 0 && dy < 0) || (dx > 0 && dy > 0) {
-                        y += 1;
-                    }
-                    else {
-                        y -= 1;
-                    }
-                    px += 2 * (dy1 - dx1);
-                }
-                if rol() { self.draw(x, y, p); }
+    #[inline]
+    fn _draw_line_pattern(&mut self, x1: i32, y1: i32, x2: i32, y2: i32, p: Pixel, pattern: u32) {
+        let (dx, dy) = (x2 as i64 - x1 as i64, y2 as i64 - y1 as i64);
+        let (dx1, dy1) = (dx.abs(), dy.abs());
+        let x_major = dy1 <= dx1;
+
+        // Bresenham, stepping along the major axis from whichever end is lower on it
+        let (start_major, start_minor, d_major, d_minor) =
+            if x_major {
+                let (x, y) = if dx >= 0 { (x1, y1) } else { (x2, y2) };
+                (x as i64, y as i64, dx1, dy1)
             }
+            else {
+                let (x, y) = if dy >= 0 { (x1, y1) } else { (x2, y2) };
+                (y as i64, x as i64, dy1, dx1)
+            };
+        let minor_step = if (dx < 0 && dy < 0) || (dx > 0 && dy > 0) { 1 } else { -1 };
+        // the x major line steps when the error hits zero, the y major one waits until it's past it
+        let threshold = if x_major { -1 } else { 0 };
+
+        // only the steps that land inside the clip rect on the major axis are walked
+        let (cx0, cy0, cx1, cy1) = self._clip_bounds();
+        let (lo, hi) = if x_major { (cx0 as i64, cx1 as i64) } else { (cy0 as i64, cy1 as i64) };
+        let first = (lo - start_major).max(0);
+        let last = (hi - 1 - start_major).min(d_major);
+        if first > last {
+            return
         }
-        else {
-            let (mut x, mut y, ye) =
-                if dy >= 0 { (x1, y1, y2) }
-                else { (x2, y2, y1) };
 
-            if rol() { self.draw(x, y, p); }
+        // jump straight to the error and minor position the line would have after `first` steps
+        let mut minor = if d_major == 0 { 0 } else { (2 * d_minor * first + d_major - threshold - 1).div_euclid(2 * d_major) };
+        let mut error = 2 * d_minor * (first + 1) - d_major - 2 * d_major * minor;
+        let mut pattern = pattern.rotate_left((first % 32) as u32);
 
-            while y < ye {
-                y += 1;
-                if py <= 0 {
-                    py += 2 * dx1;
-                }
-                else {
-                    if (dx < 0 && dy < 0) || (dx > 0 && dy > 0) {
-                        x += 1;
-                    }
-                    else {
-                        x -= 1;
-                    }
-                    py += 2 * (dx1 - dy1);
-                }
-                if rol() { self.draw(x, y, p); }
+        for k in first..=last {
+            pattern = pattern.rotate_left(1);
+            if pattern & 1 != 0 {
+                let (major, minor) = (start_major + k, start_minor + minor_step * minor);
+                // the minor position never leaves the range between the two ends, so it fits in an i32
+                if x_major { self._draw(major as i32, minor as i32, p); }
+                else { self._draw(minor as i32, major as i32, p); }
             }
+            if error > threshold {
+                minor += 1;
+                error -= 2 * d_major;
+            }
+            error += 2 * d_minor;
         }
     }
 
     #[inline]
     fn _draw_circle_mask(&mut self, x: i32, y: i32, radius: i32, p: Pixel, mask: u8) {
-        if radius == 0 { return; }
+        if radius <= 0 { return; }
 
-        let mut x0 = 0;
-        let mut y0 = radius;
-        let mut d = 3 - (2 * radius);
+        // nothing to walk if the clip rect misses the circle altogether
+        let (cx0, cy0, cx1, cy1) = self._clip_bounds();
+        let (x, y, r) = (x as i64, y as i64, radius as i64);
+        if x + r < cx0 as i64 || x - r >= cx1 as i64 || y + r < cy0 as i64 || y - r >= cy1 as i64 {
+            return
+        }
 
-        while y0 >= x0 {
-            if mask & 0x01 != 0 { self._draw(x + x0, y - y0, p); }
-            if mask & 0x02 != 0 { self._draw(x + y0, y - x0, p); }
-            if mask & 0x04 != 0 { self._draw(x + y0, y + x0, p); }
-            if mask & 0x08 != 0 { self._draw(x + x0, y + y0, p); }
-            if mask & 0x10 != 0 { self._draw(x - x0, y + y0, p); }
-            if mask & 0x20 != 0 { self._draw(x - y0, y + x0, p); }
-            if mask & 0x40 != 0 { self._draw(x - y0, y - x0, p); }
-            if mask & 0x80 != 0 { self._draw(x - x0, y - y0, p); }
+        // each octant point is only drawn if its row and column are inside the clip rect
+        let plot = |pge: &mut Self, bit: u8, px: i64, py: i64| {
+            if mask & bit != 0 && py >= cy0 as i64 && py < cy1 as i64 && px >= cx0 as i64 && px < cx1 as i64 {
+                pge._draw(px as i32, py as i32, p);
+            }
+        };
 
-            if d < 0 { d += 4 * x0 + 6; x0 += 1; }
-            else { d += 4 * (x0 - y0) + 10; x0 += 1; y0 -= 1; }
+        // Every octant moves along a row or a column by one pixel per step, so only the runs of steps
+        // where that row or column is inside the clip rect can draw anything. The walk jumps to the
+        // start of each run, rather than stepping all the way around a big circle.
+        let (left, right, top, bottom) = (cx0 as i64, cx1 as i64 - 1, cy0 as i64, cy1 as i64 - 1);
+        let mut runs = vec![];
+        if mask & 0x09 != 0 { runs.push((left - x, right - x)); }
+        if mask & 0x90 != 0 { runs.push((x - right, x - left)); }
+        if mask & 0x42 != 0 { runs.push((y - bottom, y - top)); }
+        if mask & 0x24 != 0 { runs.push((top - y, bottom - y)); }
+        runs.sort_unstable();
+
+        let mut next = 0;
+        for (first, last) in runs {
+            let mut x0 = first.max(next);
+            if x0 > last {
+                continue
+            }
+            let (mut y0, mut d) = Self::_circle_state(r, x0);
+
+            while y0 >= x0 && x0 <= last {
+                plot(self, 0x01, x + x0, y - y0);
+                plot(self, 0x02, x + y0, y - x0);
+                plot(self, 0x04, x + y0, y + x0);
+                plot(self, 0x08, x + x0, y + y0);
+                plot(self, 0x10, x - x0, y + y0);
+                plot(self, 0x20, x - y0, y + x0);
+                plot(self, 0x40, x - y0, y - x0);
+                plot(self, 0x80, x - x0, y - y0);
+
+                if d < 0 { d += 4 * x0 + 6; x0 += 1; }
+                else { d += 4 * (x0 - y0) + 10; x0 += 1; y0 -= 1; }
+            }
+            if y0 < x0 {
+                break
+            }
+            next = x0;
         }
     }
 
+    // Where the midpoint circle walk is after x0 steps: y0 is the largest value with
+    // y0^2 + (y0 - 1)^2 < 2 * (r^2 - x0^2), and d follows from x0 and y0.
+    fn _circle_state(r: i64, x0: i64) -> (i64, i64) {
+        let (r, x0) = (r as i128, x0 as i128);
+        let limit = 2 * (r * r - x0 * x0);
+        let f = |y: i128| y * y + (y - 1) * (y - 1);
+        let mut y0 = ((1.0 + (2.0 * limit as f64 - 1.0).max(0.0).sqrt()) / 2.0) as i128;
+        while f(y0 + 1) < limit { y0 += 1; }
+        while y0 > 0 && f(y0) >= limit { y0 -= 1; }
+        let d = 2 * (x0 + 1) * (x0 + 1) + f(y0) - 2 * r * r;
+        (y0 as i64, d as i64)
+    }
+
     #[inline]
     fn _fill_circle(&mut self, x: i32, y: i32, r: i32, p: Pixel) {
-        if r == 0 { return }
-        
-        let mut x0 = 0;
-        let mut y0 = r;
-        let mut d = 3 - 2 * r;
-
-        let mut draw_line = |sx: i32, ex: i32, ny: i32| {
-            for i in sx..=ex {
-                self.draw(i, ny, p);
+        if r <= 0 { return }
+
+        let (x, y, r) = (x as i64, y as i64, r as i64);
+        let (cx0, cy0, cx1, cy1) = self._clip_bounds();
+        let y_at = |x0: i64| Self::_circle_state(r, x0).0;
+        // the largest x0 up to hi that passes, for tests that pass up to some point and then stop
+        let largest = |hi: i64, pass: &dyn Fn(i64) -> bool| {
+            let (mut lo, mut hi) = (0, hi);
+            while lo < hi {
+                let mid = lo + (hi - lo + 1) / 2;
+                if pass(mid) { lo = mid } else { hi = mid - 1 }
             }
+            lo
         };
 
-        while y0 >= x0 {
-            draw_line(x - x0, x + x0, y - y0);
-            draw_line(x - y0, x + y0, y - x0);
-            draw_line(x - x0, x + x0, y + y0);
-            draw_line(x - y0, x + y0, y + x0);
-            if d < 0 {
-                d += 4 * x0 + 6;
-                x0 += 1;
+        // The midpoint walk fills row k above and below the centre from the step where x0 == k,
+        // y0 wide, and from the steps where y0 == k, x0 wide. Working out the widest of those for
+        // each row inside the clip rect saves walking all the way round a big circle.
+        let last = largest(r, &|x0| y_at(x0) >= x0);
+        for row in (y - r).max(cy0 as i64)..=(y + r).min(cy1 as i64 - 1) {
+            let k = (row - y).abs();
+            let mut half = if k <= last { y_at(k) } else { -1 };
+            let x0 = largest(last, &|x0| y_at(x0) >= k);
+            if y_at(x0) == k {
+                half = half.max(x0);
             }
-            else {
-                d += 4 * (x0 - y0) + 10;
-                x0 += 1;
-                y0 -= 1;
+            if half >= 0 {
+                let (sx, ex) = ((x - half).max(cx0 as i64), (x + half).min(cx1 as i64 - 1));
+                self._draw_span(sx as i32, ex as i32, row as i32, p);
             }
         }
     }
@@ -341,10 +578,69 @@ impl PixelGameEngine {
 
     #[inline]
     fn _fill_rect(&mut self, x: i32, y: i32, w: u32, h: u32, p: Pixel) {
-        for x in x..x + w as i32 {
-            for y in y..y + h as i32 {
-                self.draw(x, y, p);
-        }}
+        let (x, y, w, h) = match self._clip_rect(x, y, w, h) {
+            Some(rect) => rect,
+            None => return
+        };
+        match self.pixel_mode {
+            PixelMode::Normal => self.draw_target.borrow_mut().fill_rect(x, y, w, h, p),
+            PixelMode::Mask => if p.a == 255 { self.draw_target.borrow_mut().fill_rect(x, y, w, h, p) }
+            PixelMode::Alpha => self.draw_target.borrow_mut().blend_rect(x, y, w, h, p, self.blend_factor),
+            _ => for y in y..y + h as i32 {
+                for x in x..x + w as i32 {
+                    self.draw(x, y, p);
+            }}
+        }
+    }
+
+    // Normal, Mask and Alpha can copy whole rows at a time, as long as the sprite isn't being
+    // read from outside its edges, where the sample mode decides what comes back
+    fn _blit(&mut self, x: i32, y: i32, sprite: &Sprite, ox: i32, oy: i32, w: u32, h: u32) -> bool {
+        let inside = ox >= 0 && oy >= 0 && ox as i64 + w as i64 <= sprite.width() as i64 && oy as i64 + h as i64 <= sprite.height() as i64;
+        if !inside || !matches!(self.pixel_mode, PixelMode::Normal | PixelMode::Mask | PixelMode::Alpha) {
+            return false
+        }
+        let (cx, cy, w, h) = match self._clip_rect(x, y, w, h) {
+            Some(rect) => rect,
+            None => return true
+        };
+        let (ox, oy, x, y) = (ox + (cx - x), oy + (cy - y), cx, cy);
+        match self.pixel_mode {
+            PixelMode::Normal => self.draw_target.borrow_mut().blit(x, y, sprite, ox, oy, w, h),
+            PixelMode::Mask => self.draw_target.borrow_mut().blit_masked(x, y, sprite, ox, oy, w, h),
+            PixelMode::Alpha => self.draw_target.borrow_mut().blit_alpha(x, y, sprite, ox, oy, w, h, self.blend_factor),
+            _ => unreachable!()
+        }
+        true
+    }
+
+    // the current clip rectangle, already cut down to fit the draw target
+    fn _clip_bounds(&self) -> (i32, i32, i32, i32) {
+        let (w, h) = (self.get_draw_target_width().min(i32::MAX as u32) as i32, self.get_draw_target_height().min(i32::MAX as u32) as i32);
+        match self.clip_stack.last() {
+            Some(&(x0, y0, x1, y1)) => (x0.max(0), y0.max(0), x1.min(w), y1.min(h)),
+            None => (0, 0, w, h)
+        }
+    }
+
+    fn _clip_rect(&self, x: i32, y: i32, w: u32, h: u32) -> Option<(i32, i32, u32, u32)> {
+        let (cx0, cy0, cx1, cy1) = self._clip_bounds();
+        let x0 = (x as i64).max(cx0 as i64);
+        let y0 = (y as i64).max(cy0 as i64);
+        let x1 = (x as i64 + w as i64).min(cx1 as i64);
+        let y1 = (y as i64 + h as i64).min(cy1 as i64);
+        if x0 >= x1 || y0 >= y1 {
+            return None
+        }
+        Some((x0 as i32, y0 as i32, (x1 - x0) as u32, (y1 - y0) as u32))
+    }
+
+    // a horizontal line from sx to ex inclusive
+    #[inline]
+    fn _draw_span(&mut self, sx: i32, ex: i32, y: i32, p: Pixel) {
+        if ex >= sx {
+            self._fill_rect(sx, y, (ex as i64 - sx as i64 + 1) as u32, 1, p);
+        }
     }
 
     #[inline]
@@ -357,170 +653,424 @@ impl PixelGameEngine {
     #[inline]
     // algorithm from http://www.sunshine2k.de/coding/java/TriangleRasterization/TriangleRasterization.html
     fn _fill_triangle(&mut self, x1: i32, y1: i32, x2: i32, y2: i32, x3: i32, y3: i32, p: Pixel) {
-        let (v1, mut v2, v3) =
-            if y1 < y2 {
-                if y1 < y3 {
-                    if y2 < y3 { (Vi2d::new(x1, y1), Vi2d::new(x2, y2), Vi2d::new(x3, y3)) }
-                    else { (Vi2d::new(x1, y1), Vi2d::new(x3, y3), Vi2d::new(x2, y2)) }}
-                else { (Vi2d::new(x3, y3), Vi2d::new(x1, y1), Vi2d::new(x2, y2)) }
+        let mut v = [(x1 as i64, y1 as i64), (x2 as i64, y2 as i64), (x3 as i64, y3 as i64)];
+        v.sort_by_key(|&(_, y)| y);
+        let [a, b, c] = v;
+
+        // where an edge crosses a scanline, rounded towards zero
+        let edge = |(x0, y0): (i64, i64), (x1, y1): (i64, i64), y: i64| {
+            if y1 == y0 { x0 } else { (x0 as f64 + (x1 - x0) as f64 * (y - y0) as f64 / (y1 - y0) as f64) as i64 }
+        };
+
+        // the long edge is split where it crosses the middle vertex's scanline, into a flat
+        // bottomed triangle on top and a flat topped one underneath
+        let d = if a.1 == c.1 { b } else { (a.0 + ((b.1 - a.1) as f64 / (c.1 - a.1) as f64 * (c.0 - a.0) as f64) as i64, b.1) };
+
+        // each scanline inside the clip rect runs between the two edges of whichever half it's in
+        let (cx0, cy0, cx1, cy1) = self._clip_bounds();
+        for y in a.1.max(cy0 as i64)..=c.1.min(cy1 as i64 - 1) {
+            let (sx, ex) =
+                if a.1 == c.1 { (a.0.min(b.0).min(c.0), a.0.max(b.0).max(c.0)) }
+                else if y < b.1 { (edge(a, b, y), edge(a, d, y)) }
+                else { (edge(b, c, y), edge(d, c, y)) };
+            let (sx, ex) = (sx.min(ex), sx.max(ex));
+            let (sx, ex) = (sx.max(cx0 as i64), ex.min(cx1 as i64 - 1));
+            self._draw_span(sx as i32, ex as i32, y as i32, p);
+        }
+    }
+
+    // coverage scales the alpha, and modes that ignore alpha would just draw a jagged shape
+    fn _begin_aa(&mut self) -> PixelMode {
+        let m = self.pixel_mode;
+        if matches!(m, PixelMode::Normal | PixelMode::Mask) {
+            self.pixel_mode = PixelMode::Alpha;
+        }
+        m
+    }
+
+    #[inline]
+    fn _draw_coverage(&mut self, x: i32, y: i32, p: Pixel, coverage: f32) {
+        let a = (p.a as f32 * coverage.min(1.0)).round();
+        if a >= 1.0 {
+            self._draw(x, y, Pixel::rgba(p.r, p.g, p.b, a as u8));
+        }
+    }
+
+    // Xiaolin Wu's line algorithm
+    fn _draw_line_aa(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, p: Pixel) {
+        let m = self._begin_aa();
+
+        // walk along the major axis, splitting each step between the two nearest pixels on the minor one
+        let steep = (y2 - y1).abs() > (x2 - x1).abs();
+        let (mut x1, mut y1, mut x2, mut y2) = if steep { (y1, x1, y2, x2) } else { (x1, y1, x2, y2) };
+        if x1 > x2 {
+            std::mem::swap(&mut x1, &mut x2);
+            std::mem::swap(&mut y1, &mut y2);
+        }
+        let dx = x2 - x1;
+        let gradient = if dx > 0.0 { (y2 - y1) / dx } else { 0.0 };
+
+        let plot = |pge: &mut Self, x: i32, y: f32, coverage: f32| {
+            let iy = y.floor();
+            let (f, iy) = (y - iy, iy as i32);
+            if steep {
+                pge._draw_coverage(iy, x, p, (1.0 - f) * coverage);
+                pge._draw_coverage(iy + 1, x, p, f * coverage);
             }
             else {
-                if y2 < y3 {
-                    if y1 < y3 { (Vi2d::new(x2, y2), Vi2d::new(x1, y1), Vi2d::new(x3, y3)) }
-                    else { (Vi2d::new(x2, y2), Vi2d::new(x3, y3), Vi2d::new(x1, y1)) }}
-                else { (Vi2d::new(x3, y3), Vi2d::new(x2, y2), Vi2d::new(x1, y1)) }
-            };
+                pge._draw_coverage(x, iy, p, (1.0 - f) * coverage);
+                pge._draw_coverage(x, iy + 1, p, f * coverage);
+            }
+        };
 
-        let mut v4 = Vi2d::new(
-            v1.x + ((v2.y - v1.y) as f32 / (v3.y - v1.y) as f32 * (v3.x - v1.x) as f32) as i32,
-            v2.y
-        );
+        // the end pixels only get the part of the line that falls inside them
+        let (xs, xe) = (x1.round(), x2.round());
+        let ys = y1 + gradient * (xs - x1);
+        if xs == xe {
+            plot(self, xs as i32, ys, dx);
+        }
+        else {
+            plot(self, xs as i32, ys, (xs + 0.5 - x1).clamp(0.0, 1.0));
+            plot(self, xe as i32, y2 + gradient * (xe - x2), (x2 + 0.5 - xe).clamp(0.0, 1.0));
 
-        if v4.x < v2.x {
-            std::mem::swap(&mut v2, &mut v4);
+            let (cx0, cy0, cx1, cy1) = self._clip_bounds();
+            let (lo, hi) = if steep { (cy0, cy1) } else { (cx0, cx1) };
+            for x in (xs as i32 + 1).max(lo)..(xe as i32).min(hi) {
+                plot(self, x, ys + gradient * (x as f32 - xs), 1.0);
+            }
         }
-        
-        // top, bottom-flat triangle
-        {
-            let (v1, v2, v3) = (v1, v2, v4);
 
-            let invslope1 = (v2.x - v1.x) as f32 / (v2.y - v1.y) as f32;
-            let invslope2 = (v3.x - v1.x) as f32 / (v3.y - v1.y) as f32;
+        self.pixel_mode = m;
+    }
+
+    // pixels are shaded by how far their centre is from the circle, giving an outline about a pixel wide
+    fn _draw_circle_aa(&mut self, x: f32, y: f32, radius: f32, p: Pixel) {
+        if radius < 0.0 { return; }
 
-            let mut curx1 = v1.x as f32;
-            let mut curx2 = v1.x as f32;
+        let m = self._begin_aa();
+        let (cx0, cy0, cx1, cy1) = self._clip_bounds();
+        let (outer, inner) = (radius + 1.0, radius - 1.0);
 
-            for scanline_y in v1.y ..= v2.y {
-                for x in curx1 as i32 ..= curx2 as i32 {
-                    self.draw(x, scanline_y, p);
+        for py in ((y - outer).floor() as i32).max(cy0)..((y + outer).ceil() as i32 + 1).min(cy1) {
+            let dy = py as f32 - y;
+            if dy.abs() >= outer {
+                continue
+            }
+
+            // only the ring either side of the circle is visited, not the whole disc
+            let xo = (outer * outer - dy * dy).sqrt();
+            let xi = if dy.abs() < inner { (inner * inner - dy * dy).sqrt() } else { 0.0 };
+            let left_end = (x - xi).floor() as i32;
+            let spans = [((x - xo).ceil() as i32, left_end), (((x + xi).ceil() as i32).max(left_end + 1), (x + xo).floor() as i32)];
+
+            for &(sx, ex) in spans.iter() {
+                for px in sx.max(cx0)..(ex + 1).min(cx1) {
+                    let d = ((px as f32 - x).powi(2) + dy * dy).sqrt();
+                    self._draw_coverage(px, py, p, 1.0 - (d - radius).abs());
                 }
-                curx1 += invslope1;
-                curx2 += invslope2;
             }
         }
 
-        // bottom, top-flat triangle
-        {
-            let (v1, v2, v3) = (v2, v4, v3);
+        self.pixel_mode = m;
+    }
 
-            let invslope1 = (v3.x - v1.x) as f32 / (v3.y - v1.y) as f32;
-            let invslope2 = (v3.x - v2.x) as f32 / (v3.y - v2.y) as f32;
+    // Scanline fill where pixel (x, y) covers the square from (x, y) to (x + 1, y + 1), like fill_rect.
+    // Each row is sampled a few times vertically, and the spans are measured exactly across it.
+    fn _fill_polygon_aa(&mut self, points: &[Vf2d], p: Pixel) {
+        const SAMPLES: usize = 4;
 
-            let mut curx1 = v1.x as f32;
-            let mut curx2 = v2.x as f32;
+        if points.len() < 3 { return; }
 
-            for y in v1.y ..= v3.y {
-                for x in curx1 as i32 ..= curx2 as i32 {
-                    self.draw(x, y, p);
+        let (cx0, cy0, cx1, cy1) = self._clip_bounds();
+        let min_y = points.iter().map(|v| v.y).fold(f32::INFINITY, f32::min);
+        let max_y = points.iter().map(|v| v.y).fold(f32::NEG_INFINITY, f32::max);
+        if cx0 >= cx1 || !min_y.is_finite() || !max_y.is_finite() { return; }
+
+        let m = self._begin_aa();
+        let mut coverage = vec![0.0f32; (cx1 - cx0) as usize];
+        let mut crossings: Vec<f32> = vec![];
+
+        for py in (min_y.floor() as i32).max(cy0)..(max_y.ceil() as i32).min(cy1) {
+            coverage.iter_mut().for_each(|c| *c = 0.0);
+
+            for s in 0..SAMPLES {
+                let sy = py as f32 + (s as f32 + 0.5) / SAMPLES as f32;
+
+                crossings.clear();
+                for (i, a) in points.iter().enumerate() {
+                    let b = points[(i + 1) % points.len()];
+                    if (a.y <= sy) != (b.y <= sy) {
+                        crossings.push(a.x + (sy - a.y) * (b.x - a.x) / (b.y - a.y));
+                    }
+                }
+                crossings.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+                for span in crossings.chunks_exact(2) {
+                    let (xa, xb) = (span[0].max(cx0 as f32), span[1].min(cx1 as f32));
+                    if xa >= xb {
+                        continue
+                    }
+                    let (ia, ib) = (xa.floor() as i32, xb.floor() as i32);
+                    let index = |x: i32| (x - cx0) as usize;
+                    if ia == ib {
+                        coverage[index(ia)] += xb - xa;
+                        continue
+                    }
+                    coverage[index(ia)] += (ia + 1) as f32 - xa;
+                    for x in ia + 1..ib {
+                        coverage[index(x)] += 1.0;
+                    }
+                    if ib < cx1 {
+                        coverage[index(ib)] += xb - ib as f32;
+                    }
+                }
+            }
+
+            for (i, &c) in coverage.iter().enumerate() {
+                if c > 0.0 {
+                    self._draw_coverage(cx0 + i as i32, py, p, c / SAMPLES as f32);
                 }
-                curx1 += invslope1;
-                curx2 += invslope2;
             }
         }
+
+        self.pixel_mode = m;
     }
 
     #[inline]
-    fn _draw_sprite(&mut self, x: i32, y: i32, sprite: SpriteRef, scale: u32) {
+    fn _draw_sprite(&mut self, x: i32, y: i32, sprite: SpriteRef, scale: u32, flip: SpriteFlip) {
         let sprite = sprite.borrow();
-        match scale {
-            0 => (),
-            1 => for i in 0..sprite.width() as i32 {
-                for j in 0..sprite.height() as i32 {
-                    self.draw(x + i, y + j, sprite.get_pixel(i, j));
-            }}
-            _ => for i in 0..sprite.width() as i32 {
-                for j in 0..sprite.height() as i32 {
-                    for is in 0..scale as i32 {
-                        for js in 0..scale as i32 {
-                            let scale = scale as i32;
-                            self.draw(x + (i * scale) + is, y + (j * scale) + js, sprite.get_pixel(i, j));
-            }}}}
+        let (w, h) = (sprite.width(), sprite.height());
+        if scale == 1 && flip == SpriteFlip::None && self._blit(x, y, &sprite, 0, 0, w, h) {
+            return
         }
+        self._draw_sprite_scaled(x, y, &sprite, 0, 0, w, h, scale, flip);
     }
 
     #[inline]
-    fn _draw_partial_sprite(&mut self, x: i32, y: i32, sprite: SpriteRef, ox: i32, oy: i32, w: u32, h: u32, scale: u32) {
+    fn _draw_partial_sprite(&mut self, x: i32, y: i32, sprite: SpriteRef, ox: i32, oy: i32, w: u32, h: u32, scale: u32, flip: SpriteFlip) {
         let sprite = sprite.borrow();
-        match scale {
-            0 => (),
-            1 => for i in 0..w as i32 {
-                for j in 0..h as i32 {
-                    self.draw(x + i, y + j, sprite.get_pixel(i + ox, j + oy));
-            }}
-            // I didn't test this code, but it probably works.
-            _ => for i in 0..sprite.width() as i32 {
-                for j in 0..sprite.height() as i32 {
-                    let scale = scale as i32;
-                    for is in 0..scale {
-                        for js in 0..scale {
-                            self.draw(x + (i * scale) + is, y + (j * scale) + js, sprite.get_pixel(i + ox, j + oy));
-            }}}}
+        if scale == 1 && flip == SpriteFlip::None && self._blit(x, y, &sprite, ox, oy, w, h) {
+            return
+        }
+        self._draw_sprite_scaled(x, y, &sprite, ox, oy, w, h, scale, flip);
+    }
+
+    // walks the destination pixels inside the clip rect, and looks up which source pixel each one came from
+    fn _draw_sprite_scaled(&mut self, x: i32, y: i32, sprite: &Sprite, ox: i32, oy: i32, w: u32, h: u32, scale: u32, flip: SpriteFlip) {
+        if scale == 0 || w == 0 || h == 0 {
+            return
+        }
+        let (cx0, cy0, cx1, cy1) = self._clip_bounds();
+        let (x, y, scale) = (x as i64, y as i64, scale as i64);
+        let (x0, x1) = (x.max(cx0 as i64), (x + w as i64 * scale).min(cx1 as i64));
+        let (y0, y1) = (y.max(cy0 as i64), (y + h as i64 * scale).min(cy1 as i64));
+        let (w, h) = (w.min(i32::MAX as u32) as i32, h.min(i32::MAX as u32) as i32);
+
+        for py in y0..y1 {
+            let j = ((py - y) / scale) as i32;
+            for px in x0..x1 {
+                let i = ((px - x) / scale) as i32;
+                let (fi, fj) = flip.apply(i, j, w, h);
+                self._draw(px as i32, py as i32, sprite.get_pixel(fi + ox, fj + oy));
+            }
+        }
+    }
+
+    // every pixel whose centre lands inside the scaled rectangle is mapped back into the source rectangle
+    fn _draw_sprite_ex(&mut self, pos: Vf2d, sprite: SpriteRef, source_pos: Vi2d, source_size: Vi2d, scale: Vf2d, flip: SpriteFlip, filter: SpriteFilter) {
+        let sprite = sprite.borrow();
+        let (sw, sh) = (source_size.x as f32, source_size.y as f32);
+        if scale.x <= 0.0 || scale.y <= 0.0 || source_size.x <= 0 || source_size.y <= 0 {
+            return
+        }
+
+        let (cx0, cy0, cx1, cy1) = self._clip_bounds();
+        let min_x = (pos.x - 0.5).ceil().max(cx0 as f32) as i32;
+        let min_y = (pos.y - 0.5).ceil().max(cy0 as f32) as i32;
+        let max_x = (pos.x + sw * scale.x - 0.5).ceil().min(cx1 as f32) as i32;
+        let max_y = (pos.y + sh * scale.y - 0.5).ceil().min(cy1 as f32) as i32;
+
+        let flip_x = matches!(flip, SpriteFlip::Horizontal | SpriteFlip::Both);
+        let flip_y = matches!(flip, SpriteFlip::Vertical | SpriteFlip::Both);
+
+        // bilinear filtering stays inside the source rectangle so neighbouring tiles don't bleed in
+        let last = source_pos + source_size - (1, 1);
+        let fetch = |x: i32, y: i32| sprite.get_pixel(x.max(source_pos.x).min(last.x), y.max(source_pos.y).min(last.y));
+
+        for y in min_y..max_y {
+            let v = ((y as f32 + 0.5 - pos.y) / scale.y).min(sh);
+            let v = if flip_y { sh - v } else { v };
+            for x in min_x..max_x {
+                let u = ((x as f32 + 0.5 - pos.x) / scale.x).min(sw);
+                let u = if flip_x { sw - u } else { u };
+
+                let p = match filter {
+                    SpriteFilter::Nearest => fetch(source_pos.x + u as i32, source_pos.y + v as i32),
+                    SpriteFilter::Bilinear => {
+                        let (u, v) = (u - 0.5, v - 0.5);
+                        let (i, j) = (source_pos.x + u.floor() as i32, source_pos.y + v.floor() as i32);
+                        let (fu, fv) = (u - u.floor(), v - v.floor());
+                        let top = pixel_lerp(fetch(i, j), fetch(i + 1, j), fu);
+                        let bottom = pixel_lerp(fetch(i, j + 1), fetch(i + 1, j + 1), fu);
+                        pixel_lerp(top, bottom, fv)
+                    }
+                };
+                self.draw(x, y, p);
+            }
+        }
+    }
+
+    // every pixel in the rotated sprite's bounding box is mapped back into the sprite
+    fn _draw_sprite_rotated(&mut self, pos: Vf2d, sprite: SpriteRef, angle: f32, origin: Vf2d, scale: f32, filter: SpriteFilter) {
+        let sprite = sprite.borrow();
+        let (w, h) = (sprite.width() as f32, sprite.height() as f32);
+        if scale <= 0.0 || w == 0.0 || h == 0.0 {
+            return
+        }
+
+        let (sin, cos) = angle.sin_cos();
+        let corners = [(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)].map(|(x, y)| {
+            let (x, y) = ((x - origin.x) * scale, (y - origin.y) * scale);
+            (pos.x + x * cos - y * sin, pos.y + x * sin + y * cos)
+        });
+        let (cx0, cy0, cx1, cy1) = self._clip_bounds();
+        let min_x = corners.iter().map(|c| c.0).fold(f32::INFINITY, f32::min).floor().max(cx0 as f32) as i32;
+        let min_y = corners.iter().map(|c| c.1).fold(f32::INFINITY, f32::min).floor().max(cy0 as f32) as i32;
+        let max_x = corners.iter().map(|c| c.0).fold(f32::NEG_INFINITY, f32::max).ceil().min(cx1 as f32) as i32;
+        let max_y = corners.iter().map(|c| c.1).fold(f32::NEG_INFINITY, f32::max).ceil().min(cy1 as f32) as i32;
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let (dx, dy) = (x as f32 + 0.5 - pos.x, y as f32 + 0.5 - pos.y);
+                let u = (dx * cos + dy * sin) / scale + origin.x;
+                let v = (dy * cos - dx * sin) / scale + origin.y;
+                if u < 0.0 || u >= w || v < 0.0 || v >= h {
+                    continue
+                }
+                let p = match filter {
+                    SpriteFilter::Nearest => sprite.get_pixel(u as i32, v as i32),
+                    SpriteFilter::Bilinear => sprite.sample_bl(u / w, v / h)
+                };
+                self.draw(x, y, p);
+            }
         }
     }
 
     #[inline]
-    fn _draw_string_scaled(&mut self, x: i32, y: i32, text: &String, p: Pixel, scale: u32) {
-        let mut sx = 0;
-        let mut sy = 0;
+    fn _draw_string_scaled(&mut self, x: i32, y: i32, text: &str, p: Pixel, scale: u32) {
+        let mut sx: i64 = 0;
+        let mut sy: i64 = 0;
         let m = self.get_pixel_mode();
 
         if p.a != 255 { self.set_pixel_mode(PixelMode::Alpha) }
         else { self.set_pixel_mode(PixelMode::Mask) }
 
+        // only the part of each glyph inside the clip rect is visited
+        let (cx0, cy0, cx1, cy1) = self._clip_bounds();
+        let glyph_scale = scale.max(1) as i64;
+        let size = 8 * glyph_scale;
+
         for c in text.bytes() {
             if c == b'\n' {
-                sx = 0; sy += 8 * scale;
+                sx = 0; sy += 8 * scale as i64;
             }
             else {
-                if scale > 1 {
-                    for i in 0..8 {
-                        for j in 0..8 {
-                            let index = (c as usize * 64) + (j * 8) + i;
-                            if FONT_DATA[index] == 1 {
-                                for is in 0..scale {
-                                    for js in 0..scale {
-                                        let px = x + (sx + (i as u32 * scale) + is) as i32;
-                                        let py = y + (sy + (j as u32 * scale) + js) as i32;
-                                        self.draw(px, py, p);
-                }}}}}}
-                else {
-                    for i in 0..8 {
-                        for j in 0..8 {
-                            let index = (c as usize * 64) + (j * 8) + i;
-                            if FONT_DATA[index] == 1 {
-                                self.draw(x + sx as i32 + i as i32, y + sy as i32 + j as i32, p);
-                }}}}
-                sx += 8 * scale;
+                // bytes the font doesn't cover, like the parts of a multi byte character, leave a gap
+                let (gx, gy) = (x as i64 + sx, y as i64 + sy);
+                if (c as usize) < FONT_DATA.len() / 64 {
+                    for py in gy.max(cy0 as i64)..(gy + size).min(cy1 as i64) {
+                        let j = ((py - gy) / glyph_scale) as usize;
+                        for px in gx.max(cx0 as i64)..(gx + size).min(cx1 as i64) {
+                            let i = ((px - gx) / glyph_scale) as usize;
+                            if FONT_DATA[(c as usize * 64) + (j * 8) + i] == 1 {
+                                self._draw(px as i32, py as i32, p);
+                            }
+                        }
+                    }
+                }
+                sx += 8 * scale as i64;
             }
         }
 
         self.set_pixel_mode(m);
     }
 
+    fn _queue_decal(&mut self, decal: &Decal, pos: [Vf2d; 4], source_pos: Vf2d, source_size: Vf2d, tint: Pixel) {
+        let instance = DecalInstance { decal: decal.clone(), pos, source_pos, source_size, tint };
+        self.layers[self.target_layer].decal_instances.push(instance);
+    }
+
+    fn _draw_partial_decal(&mut self, pos: Vf2d, decal: &Decal, source_pos: Vf2d, source_size: Vf2d, scale: Vf2d, tint: Pixel) {
+        let (w, h) = (source_size.x * scale.x, source_size.y * scale.y);
+        let corners = [pos, pos + (w, 0.0), pos + (w, h), pos + (0.0, h)];
+        self._queue_decal(decal, corners, source_pos, source_size, tint);
+    }
+
+    // center is in decal pixels, and ends up at pos
+    fn _draw_rotated_decal(&mut self, pos: Vf2d, decal: &Decal, angle: f32, center: Vf2d, scale: Vf2d, tint: Pixel) {
+        let (w, h) = (decal.width() as f32, decal.height() as f32);
+        let (sin, cos) = angle.sin_cos();
+        let corners = [(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)].map(|(x, y)| {
+            let (x, y) = ((x - center.x) * scale.x, (y - center.y) * scale.y);
+            pos + (x * cos - y * sin, x * sin + y * cos)
+        });
+        self._queue_decal(decal, corners, Vf2d::new(0.0, 0.0), Vf2d::new(w, h), tint);
+    }
+
+    fn _draw_string_decal(&mut self, pos: Vf2d, text: &str, col: Pixel, scale: Vf2d) {
+        let font = self.font_decal.clone();
+        let mut offset = Vf2d::new(0.0, 0.0);
+        for c in text.bytes() {
+            if c == b'\n' {
+                offset = Vf2d::new(0.0, offset.y + 8.0 * scale.y);
+                continue
+            }
+            if c != b' ' && (c as usize) < FONT_DATA.len() / 64 {
+                let source_pos = Vf2d::new((c % 16) as f32 * 8.0, (c / 16) as f32 * 8.0);
+                self._draw_partial_decal(pos + offset, &font, source_pos, Vf2d::new(8.0, 8.0), scale, col);
+            }
+            offset.x += 8.0 * scale.x;
+        }
+    }
+
+    // the font laid out 16 characters to a row, white on transparent so it can be tinted
+    fn _create_font_decal() -> Decal {
+        let mut sprite = Sprite::new(128, 64);
+        for (c, glyph) in FONT_DATA.chunks(64).enumerate() {
+            for (i, &bit) in glyph.iter().enumerate() {
+                if bit == 1 {
+                    let x = (c % 16) * 8 + i % 8;
+                    let y = (c / 16) * 8 + i / 8;
+                    sprite.set_pixel(x as i32, y as i32, WHITE);
+                }
+            }
+        }
+        Decal::new(sprite.into_ref())
+    }
+
     #[inline]
     fn _clear(&mut self, p: Pixel) {
-        let mut draw_target = self.draw_target.borrow_mut();
-        
-        for y in 0..draw_target.height() {
-            for x in 0..draw_target.width() {
-                draw_target.set_pixel(x as i32, y as i32, p);
-            }
+        if self.clip_stack.is_empty() {
+            self.draw_target.borrow_mut().clear(p);
+        }
+        else if let Some((x, y, w, h)) = self._clip_rect(0, 0, u32::MAX, u32::MAX) {
+            self.draw_target.borrow_mut().fill_rect(x, y, w, h, p);
         }
     }
 
     fn _update_keys(&mut self) {
         self.keyboard_state = [ HWButton::default(); Key::Count as usize];
 
-        for key in self.window.get_keys().unwrap() {
-            self.keyboard_state[KEYMAP[key as usize] as usize].held = true;
+        for key in self.platform.get_keys() {
+            self.keyboard_state[key as usize].held = true;
         }
 
-        for key in self.window.get_keys_pressed(minifb::KeyRepeat::No).unwrap() {
-            self.keyboard_state[KEYMAP[key as usize] as usize].pressed = true;
+        for key in self.platform.get_keys_pressed() {
+            self.keyboard_state[key as usize].pressed = true;
         }
 
-        for key in self.window.get_keys_released().unwrap() {
-            self.keyboard_state[KEYMAP[key as usize] as usize].released = true;
+        for key in self.platform.get_keys_released() {
+            self.keyboard_state[key as usize].released = true;
         }
 
         self.keyboard_state[Key::Alt as usize] = HWButton {
@@ -554,17 +1104,65 @@ impl PixelGameEngine {
         };
     }
 
-    fn _update_mouse(&mut self) {
-        use minifb::{MouseButton, MouseMode};
+    fn _target_frame_time(&self) -> Option<Duration> {
+        if self.platform.fixed_frame_time().is_some() {
+            return None
+        }
+
+        let vsync = if self.vsync && !self.native_vsync { Some(self.refresh_rate) } else { None };
+        let fps = match (self.frame_cap, vsync) {
+            (Some(cap), Some(rate)) => Some(cap.min(rate)),
+            (cap, rate) => cap.or(rate)
+        };
+
+        fps.map(|fps| Duration::from_secs_f32(1.0 / fps))
+    }
+
+    // resizable and fullscreen windows are scaled by the engine instead of the platform
+    fn _is_scaled(&self) -> bool { self.resizable || self.fullscreen }
+
+    fn _update_viewport(&mut self) {
+        let (window_w, window_h) = self.platform.get_size();
+
+        if !self._is_scaled() || window_w == 0 || window_h == 0 {
+            self.viewport_pos = Vi2d::new(0, 0);
+            self.viewport_size = Vi2d::new(window_w as i32, window_h as i32);
+            return
+        }
 
-        if let Some((x, y)) = self.window.get_mouse_pos(MouseMode::Discard) {
-            self.mouse_pos_x = x as i32 / self.pixel_w as i32;
-            self.mouse_pos_y = y as i32 / self.pixel_h as i32;
+        // the pixel aspect ratio is kept, so integer scaling works in steps of the reduced pixel size
+        let gcd = |mut a: usize, mut b: usize| { while b != 0 { let t = a % b; a = b; b = t; } a.max(1) };
+        let g = gcd(self.pixel_w, self.pixel_h);
+        let (step_w, step_h) = (self.screen_w * self.pixel_w / g, self.screen_h * self.pixel_h / g);
+        let scale = (window_w / step_w.max(1)).min(window_h / step_h.max(1));
+
+        let (w, h) = if self.integer_scaling && scale > 0 {
+            (step_w * scale, step_h * scale)
+        }
+        else {
+            let base_w = (self.screen_w * self.pixel_w).max(1) as f32;
+            let base_h = (self.screen_h * self.pixel_h).max(1) as f32;
+            let scale = (window_w as f32 / base_w).min(window_h as f32 / base_h);
+            ((base_w * scale) as usize, (base_h * scale) as usize)
+        };
+
+        self.viewport_pos = Vi2d::new((window_w.saturating_sub(w) / 2) as i32, (window_h.saturating_sub(h) / 2) as i32);
+        self.viewport_size = Vi2d::new(w as i32, h as i32);
+    }
+
+    fn _update_mouse(&mut self) {
+        if let Some((x, y)) = self.platform.get_mouse_pos() {
+            if self.viewport_size.x > 0 && self.viewport_size.y > 0 {
+                let x = x - self.viewport_pos.x as f32;
+                let y = y - self.viewport_pos.y as f32;
+                self.mouse_pos_x = (x * self.screen_w as f32 / self.viewport_size.x as f32).floor() as i32;
+                self.mouse_pos_y = (y * self.screen_h as f32 / self.viewport_size.y as f32).floor() as i32;
+            }
         }
 
-        for (i, button) in [MouseButton::Left, MouseButton::Right, MouseButton::Middle].iter().enumerate() {
+        for i in 0..3 {
             let old = self.mouse_state[i];
-            let current = self.window.get_mouse_down(*button);
+            let current = self.platform.get_mouse_down(i);
             self.mouse_state[i] = HWButton {
                 held: current & (old.pressed | old.held),
                 pressed: current & !(old.pressed | old.held),
@@ -572,17 +1170,59 @@ impl PixelGameEngine {
             }
         }
 
-        if let Some((_, y)) = self.window.get_scroll_wheel() {
-            self.mouse_wheel_delta = y as i32;
+        self.mouse_wheel_delta = self.platform.get_mouse_wheel();
+    }
+
+    // layers and decals are drawn onto a separate frame, so the layers themselves are left as they were
+    fn _composite(&mut self) {
+        layer::composite(&self.layers, &mut self.composite);
+    }
+
+    fn _update_window(&mut self) -> PlatformResult {
+        // the app may have resized the window since the frame started
+        self._update_viewport();
+        self._composite();
+        let frame_buffer = &self.composite;
+        let (window_w, window_h) = self.platform.get_size();
+
+        let offset = self.sub_pixel_offset != Vf2d::new(0.0, 0.0);
+        if (!self._is_scaled() && !offset) || self.viewport_size.x <= 0 || self.viewport_size.y <= 0 {
+            return self.platform.present(frame_buffer.get_data(), self.screen_w, self.screen_h)
         }
-        else {
-            self.mouse_wheel_delta = 0;
+
+        // letterbox the screen into the middle of the window with nearest neighbour scaling
+        let black: u32 = BLACK.into();
+        self.present_buffer.clear();
+        self.present_buffer.resize(window_w * window_h, black);
+
+        let (vx, vy) = ((self.viewport_pos.x as usize).min(window_w), (self.viewport_pos.y as usize).min(window_h));
+        let (vw, vh) = (self.viewport_size.x as usize, self.viewport_size.y as usize);
+        let src = frame_buffer.get_data();
+
+        // the sub pixel offset slides the frame around inside the viewport, leaving black at the edges
+        let shift_x = (self.sub_pixel_offset.x * vw as f32 / self.screen_w as f32).round() as isize;
+        let shift_y = (self.sub_pixel_offset.y * vh as f32 / self.screen_h as f32).round() as isize;
+        let unshift = |p: usize, shift: isize, size: usize| {
+            let p = p as isize - shift;
+            if p >= 0 && (p as usize) < size { Some(p as usize) } else { None }
+        };
+
+        let columns: Vec<Option<usize>> = (0..vw).map(|x| unshift(x, shift_x, vw).map(|x| x * self.screen_w / vw)).collect();
+        for y in 0..vh.min(window_h - vy) {
+            let sy = match unshift(y, shift_y, vh) {
+                Some(sy) => sy * self.screen_h / vh,
+                None => continue
+            };
+            let src_row = &src[sy * self.screen_w..][..self.screen_w];
+            let dst_row = &mut self.present_buffer[(vy + y) * window_w + vx..][..vw.min(window_w - vx)];
+            for (dst, sx) in dst_row.iter_mut().zip(columns.iter()) {
+                if let Some(sx) = sx {
+                    *dst = src_row[*sx];
+                }
+            }
         }
-    }
 
-    fn _update_window(&mut self) -> minifb::Result<()> {
-        let frame_buffer = self.default_draw_target.borrow_mut();
-        self.window.update_with_buffer(frame_buffer.get_data(), self.screen_w, self.screen_h)
+        self.platform.present(&self.present_buffer, window_w, window_h)
     }
 }
 
@@ -593,30 +1233,62 @@ pub struct PixelGameEngineContext<App: PGEApplication> {
     frame_instant: Instant,
     frame_timer: f32,
     frame_count: u32,
+    fixed_accumulator: f32,
+
+    recorder: Option<Recorder>,
 }
 
 impl<App: PGEApplication> PixelGameEngineContext<App> {
-    fn core_update(&mut self) {
+    fn core_update(&mut self) -> Result<(), PgeError> {
         // handle when the window closes
-        if !self.engine.window.is_open() {
+        if !self.engine.platform.is_open() {
             self.engine.active = false;
-            return
+            return Ok(())
         }
 
         // handle timing
-        let elapsed_time = self.frame_instant.elapsed().as_micros() as f32 / 1_000_000.0;
+        let elapsed_time = self.engine.platform.fixed_frame_time()
+            .unwrap_or_else(|| self.frame_instant.elapsed().as_micros() as f32 / 1_000_000.0);
         self.frame_instant = Instant::now();
 
         // handle hardware
+        self.engine.platform.poll_events();
+        self.engine._update_viewport();
         self.engine._update_mouse();
         self.engine._update_keys();
 
+        // decals last until the next frame starts, so a screenshot between frames still has them
+        for layer in self.engine.layers.iter_mut() {
+            layer.decal_instances.clear();
+        }
+
+        // run as many fixed steps as have built up, dropping the rest if it falls too far behind
+        self.fixed_accumulator += elapsed_time;
+        let mut steps = 0;
+        while self.fixed_accumulator >= self.engine.fixed_step {
+            let step = self.engine.fixed_step;
+            if steps == self.engine.max_fixed_steps {
+                self.fixed_accumulator %= step;
+                break
+            }
+            if !self.app.on_fixed_update(&mut self.engine, step) {
+                self.engine.active = false;
+                break
+            }
+            self.fixed_accumulator -= step;
+            steps += 1;
+        }
+        self.engine.fixed_alpha = self.fixed_accumulator / self.engine.fixed_step;
+
         if !self.app.on_user_update(&mut self.engine, elapsed_time) {
             self.engine.active = false;
         }
 
-        if let Err(_) = self.engine._update_window() {
-            self.engine.active = false;
+        self.engine._update_window().map_err(PgeError::Backend)?;
+
+        if let Err(e) = self.update_recording(elapsed_time) {
+            let _ = self.stop_recording();
+            self.engine.recording_error = Some(e);
         }
 
         // update title bar with fps
@@ -625,38 +1297,171 @@ impl<App: PGEApplication> PixelGameEngineContext<App> {
 
         if self.frame_timer >= 1.0 {
             self.frame_timer -= 1.0;
-            self.engine.window.set_title(
-                format!("OneLoneCoder.com - Pixel Game Engine - {} - FPS: {}",
-                App::APP_NAME,
-                self.frame_count).as_str()
-            );
+            if self.engine.show_fps {
+                self.engine.platform.set_title(
+                    format!("{} - FPS: {}",
+                    self.engine.title,
+                    self.frame_count).as_str()
+                );
+            }
             self.frame_count = 0;
         }
+
+        self.wait_for_next_frame();
+
+        Ok(())
     }
 
-    pub fn start(&mut self) -> RCode {
+    // a recording started this frame includes it, and one stopped this frame ends with it
+    fn update_recording(&mut self, elapsed_time: f32) -> Result<(), PgeError> {
+        let mut stop = false;
+        match self.engine.recording_request.take() {
+            Some(RecordingRequest::Start(recorder)) => self.start_recording(*recorder)?,
+            Some(RecordingRequest::Stop) => stop = true,
+            None => ()
+        }
+
+        if let Some(recorder) = self.recorder.as_mut() {
+            if !recorder.capture(&self.engine.composite, elapsed_time)? {
+                stop = true;
+            }
+        }
+
+        if stop {
+            self.stop_recording()?;
+        }
+        Ok(())
+    }
+
+    fn wait_for_next_frame(&self) {
+        if let Some(target) = self.engine._target_frame_time() {
+            let deadline = self.frame_instant + target;
+            loop {
+                let now = Instant::now();
+                if now >= deadline {
+                    break
+                }
+                let remaining = deadline - now;
+                if remaining > SPIN_TIME {
+                    std::thread::sleep(remaining - SPIN_TIME);
+                }
+                else {
+                    std::thread::yield_now();
+                }
+            }
+        }
+    }
+
+    pub fn start(&mut self) -> Result<(), PgeError> {
         if !self.app.on_user_create(&mut self.engine) {
-            return RCode::Fail
+            return Err(PgeError::UserAbort)
         }
 
         while self.engine.active {
             while self.engine.active {
-                self.core_update()
+                if let Err(e) = self.core_update() {
+                    self.app.on_user_destroy();
+                    let _ = self.stop_recording();
+                    return Err(e)
+                }
             }
 
-            if !self.app.on_user_destroy() && self.engine.window.is_open() {
+            if !self.app.on_user_destroy() && self.engine.platform.is_open() {
                 self.engine.active = true
             }
         }
 
-        RCode::Ok
+        self.stop_recording()
+    }
+
+    // the recording is written out when it's stopped, runs out of time, or the engine shuts down
+    pub fn start_recording(&mut self, mut recorder: Recorder) -> Result<(), PgeError> {
+        self.stop_recording()?;
+        recorder.begin()?;
+        self.recorder = Some(recorder);
+        self.engine.recording = true;
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) -> Result<(), PgeError> {
+        self.engine.recording = false;
+        match self.recorder.take() {
+            Some(mut recorder) => recorder.finish(),
+            None => Ok(())
+        }
     }
+
+    pub fn is_recording(&self) -> bool { self.recorder.is_some() }
+
+    pub fn app(&self) -> &App { &self.app }
+    pub fn app_mut(&mut self) -> &mut App { &mut self.app }
+    pub fn engine(&self) -> &PixelGameEngine { &self.engine }
+    pub fn engine_mut(&mut self) -> &mut PixelGameEngine { &mut self.engine }
 }
 
 impl PixelGameEngine {
+    #[cfg(feature = "minifb")]
     pub fn construct<App: PGEApplication>(app: App, width: usize, height: usize, pixel_width: usize, pixel_height: usize) -> PixelGameEngineContext<App> {
-        let frame_buffer = Sprite::new(width as u32, height as u32).into_ref();
-        
+        Self::construct_with_config(app, EngineConfig::new(width, height, pixel_width, pixel_height))
+    }
+
+    #[cfg(feature = "minifb")]
+    pub fn try_construct<App: PGEApplication>(app: App, width: usize, height: usize, pixel_width: usize, pixel_height: usize) -> Result<PixelGameEngineContext<App>, PgeError> {
+        Self::try_construct_with_config(app, EngineConfig::new(width, height, pixel_width, pixel_height))
+    }
+
+    #[cfg(feature = "minifb")]
+    pub fn construct_fullscreen<App: PGEApplication>(app: App, width: usize, height: usize, pixel_width: usize, pixel_height: usize) -> PixelGameEngineContext<App> {
+        Self::construct_with_config(app, EngineConfig::new(width, height, pixel_width, pixel_height).fullscreen(true))
+    }
+
+    #[cfg(feature = "minifb")]
+    pub fn construct_with_config<App: PGEApplication>(app: App, config: EngineConfig) -> PixelGameEngineContext<App> {
+        Self::try_construct_with_config(app, config).unwrap_or_else(|e| {
+            panic!("{}", e)
+        })
+    }
+
+    #[cfg(feature = "minifb")]
+    pub fn try_construct_with_config<App: PGEApplication>(app: App, config: EngineConfig) -> Result<PixelGameEngineContext<App>, PgeError> {
+        let platform = MinifbPlatform::new(&Self::_title::<App>(&config), &config)
+            .map_err(|e| PgeError::WindowCreation(Box::new(e)))?;
+
+        Self::try_construct_with_platform(app, config, platform)
+    }
+
+    pub fn construct_headless<App, I>(app: App, width: usize, height: usize, frames: Option<u32>, input: I) -> PixelGameEngineContext<App>
+    where
+        App: PGEApplication,
+        I: IntoIterator<Item = InputFrame>,
+        I::IntoIter: 'static
+    {
+        Self::construct_with_platform(app, EngineConfig::new(width, height, 1, 1), HeadlessPlatform::new(frames, input))
+    }
+
+    pub fn construct_with_platform<App: PGEApplication, P: Platform + 'static>(app: App, config: EngineConfig, platform: P) -> PixelGameEngineContext<App> {
+        Self::try_construct_with_platform(app, config, platform).unwrap_or_else(|e| {
+            panic!("{}", e)
+        })
+    }
+
+    pub fn try_construct_with_platform<App: PGEApplication, P: Platform + 'static>(app: App, config: EngineConfig, mut platform: P) -> Result<PixelGameEngineContext<App>, PgeError> {
+        let (width, height) = (config.screen_w, config.screen_h);
+        let (pixel_width, pixel_height) = (config.pixel_w, config.pixel_h);
+        let title = Self::_title::<App>(&config);
+
+        platform.set_size(width * pixel_width, height * pixel_height)
+            .and_then(|_| platform.set_resizable(config.resizable))
+            .and_then(|_| platform.set_fullscreen(config.fullscreen))
+            .map_err(PgeError::WindowCreation)?;
+        platform.set_title(&title);
+
+        let mut frame_buffer = Sprite::new(width as u32, height as u32);
+        frame_buffer.clear(config.clear_colour);
+        let frame_buffer = frame_buffer.into_ref();
+        let mut screen_layer = Layer::new(frame_buffer.clone());
+        screen_layer.enabled = true;
+
         let mut context = PixelGameEngineContext {
             engine: PixelGameEngine {
                 screen_w: width,
@@ -675,35 +1480,62 @@ impl PixelGameEngine {
                 default_draw_target: frame_buffer.clone(),
                 draw_target: frame_buffer,
 
-                pixel_mode: PixelMode::Normal,
+                pixel_mode: config.pixel_mode,
+                custom_pixel_mode: None,
                 blend_factor: 1.0,
-    
-                window: minifb::Window::new(
-                    App::APP_NAME,
-                    width * pixel_width, height * pixel_height,
-                    minifb::WindowOptions {
-                        borderless: false,
-                        title: true,
-                        resize: false,
-                        scale: minifb::Scale::X1,
-                        scale_mode: minifb::ScaleMode::Stretch,
-                        topmost: false,
-                        transparency: false,
-                        none: false
-                    }
-                ).unwrap_or_else(|e| {
-                    panic!("{}", e)
-                }),
+                clip_stack: vec![],
+
+                layers: vec![screen_layer],
+                target_layer: 0,
+                font_decal: Self::_create_font_decal(),
+                composite: Sprite::new(width as u32, height as u32),
+
+                resizable: config.resizable,
+                fullscreen: config.fullscreen,
+                integer_scaling: config.integer_scaling,
+                viewport_pos: Vi2d::new(0, 0),
+                viewport_size: Vi2d::new((width * pixel_width) as i32, (height * pixel_height) as i32),
+                sub_pixel_offset: Vf2d::new(0.0, 0.0),
+                present_buffer: vec![],
+
+                recording: false,
+                recording_request: None,
+                recording_error: None,
+
+                title,
+                show_fps: config.show_fps,
+
+                frame_cap: None,
+                vsync: false,
+                native_vsync: false,
+                refresh_rate: DEFAULT_REFRESH_RATE,
+
+                fixed_step: config.fixed_step.max(f32::EPSILON),
+                max_fixed_steps: config.max_fixed_steps,
+                fixed_alpha: 0.0,
+
+                platform: Box::new(platform)
             },
             app,
 
             frame_instant: Instant::now(),
             frame_timer: 0.0,
             frame_count: 0,
+            fixed_accumulator: 0.0,
+
+            recorder: None,
         };
 
-        context.engine.window.limit_update_rate(None);
+        context.engine.set_frame_cap(config.frame_cap);
+        context.engine.set_vsync(config.vsync);
 
-        context
+        Ok(context)
     }
-}
\ No newline at end of file
+
+    fn _title<App: PGEApplication>(config: &EngineConfig) -> String {
+        match &config.title {
+            Some(title) => title.clone(),
+            None => format!("OneLoneCoder.com - Pixel Game Engine - {}", App::APP_NAME)
+        }
+    }
+}
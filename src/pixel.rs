@@ -13,15 +13,55 @@ impl Pixel {
     }
 }
 
+pub fn pixel_lerp(p1: Pixel, p2: Pixel, t: f32) -> Pixel {
+    let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    Pixel::rgba(lerp(p1.r, p2.r), lerp(p1.g, p2.g), lerp(p1.b, p2.b), lerp(p1.a, p2.a))
+}
+
+// what PixelMode::Alpha does, which always ends up opaque like the original
+pub(crate) fn blend_alpha(src: Pixel, dst: Pixel, blend: f32) -> Pixel {
+    let a = (src.a as f32 / 255.0) * blend;
+    let c = 1.0 - a;
+    let r = a * src.r as f32 + c * dst.r as f32;
+    let g = a * src.g as f32 + c * dst.g as f32;
+    let b = a * src.b as f32 + c * dst.b as f32;
+    Pixel::rgb(r as u8, g as u8, b as u8)
+}
+
+// Porter-Duff "over", keeping the destination's alpha
+pub fn blend_over(src: Pixel, dst: Pixel, blend: f32) -> Pixel {
+    if src.a == 255 && blend >= 1.0 {
+        return src
+    }
+    let a = src.a as f32 / 255.0 * blend;
+    let da = dst.a as f32 / 255.0 * (1.0 - a);
+    let out_a = a + da;
+    if out_a <= 0.0 {
+        return Pixel::rgba(0, 0, 0, 0)
+    }
+    let mix = |s: u8, d: u8| ((s as f32 * a + d as f32 * da) / out_a).round() as u8;
+    Pixel::rgba(mix(src.r, dst.r), mix(src.g, dst.g), mix(src.b, dst.b), (out_a * 255.0).round() as u8)
+}
+
+// runs op on each colour channel, then fades from the old colour to the new one by the source's alpha
+pub(crate) fn blend_channels(src: Pixel, dst: Pixel, blend: f32, op: impl Fn(f32, f32) -> f32) -> Pixel {
+    let a = src.a as f32 / 255.0 * blend;
+    let mix = |s: u8, d: u8| {
+        let d = d as f32;
+        (d + (op(s as f32, d).clamp(0.0, 255.0) - d) * a).round() as u8
+    };
+    Pixel::rgba(mix(src.r, dst.r), mix(src.g, dst.g), mix(src.b, dst.b), dst.a)
+}
+
 impl Default for Pixel {
     fn default() -> Self {
         Pixel::rgb(0, 0, 0)
     }
 }
 
-impl Into<u32> for Pixel {
-    fn into(self) -> u32 {
-        (self.a as u32) << 24 | (self.r as u32) << 16 | (self.g as u32) << 8 | (self.b as u32)
+impl From<Pixel> for u32 {
+    fn from(p: Pixel) -> Self {
+        (p.a as u32) << 24 | (p.r as u32) << 16 | (p.g as u32) << 8 | (p.b as u32)
     }
 }
 
@@ -33,7 +73,15 @@ impl From<u32> for Pixel {
 
 #[derive(Debug, Copy, Clone)]
 pub enum PixelMode {
-    Normal, Mask, Alpha, Custom
+    Normal, Mask, Alpha, Custom,
+    Additive, Multiply, Screen, Subtract, Min, Max, Over
+}
+
+// called with the position, the pixel being drawn, and the pixel already there
+pub(crate) struct CustomPixelMode(pub(crate) Box<dyn Fn(i32, i32, Pixel, Pixel) -> Pixel>);
+
+impl std::fmt::Debug for CustomPixelMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { f.write_str("CustomPixelMode") }
 }
 
 pub const GREY:              Pixel = Pixel { a: 255, r: 192, g: 192, b: 192 };
@@ -0,0 +1,38 @@
+use crate::Key;
+
+pub mod headless;
+#[cfg(feature = "minifb")]
+pub mod minifb;
+
+pub type PlatformResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+// Mouse positions are in window pixels, the engine maps them back to screen pixels.
+pub trait Platform: std::fmt::Debug {
+    fn is_open(&self) -> bool;
+    fn is_focused(&mut self) -> bool;
+    fn set_title(&mut self, title: &str);
+
+    fn get_size(&self) -> (usize, usize);
+    fn set_size(&mut self, width: usize, height: usize) -> PlatformResult;
+    fn set_resizable(&mut self, resizable: bool) -> PlatformResult;
+    fn set_fullscreen(&mut self, fullscreen: bool) -> PlatformResult;
+
+    // Returns true if the platform waits for the display itself,
+    // otherwise the engine paces frames to get_refresh_rate().
+    fn set_vsync(&mut self, _vsync: bool) -> bool { false }
+    fn get_refresh_rate(&self) -> Option<f32> { None }
+
+    fn poll_events(&mut self) {}
+    fn present(&mut self, buffer: &[u32], width: usize, height: usize) -> PlatformResult;
+
+    fn get_keys(&self) -> Vec<Key>;
+    fn get_keys_pressed(&self) -> Vec<Key>;
+    fn get_keys_released(&self) -> Vec<Key>;
+
+    fn get_mouse_pos(&self) -> Option<(f32, f32)>;
+    fn get_mouse_down(&self, button: usize) -> bool;
+    fn get_mouse_wheel(&self) -> i32;
+
+    // Platforms that don't run in real time can hand out a fixed frame time instead.
+    fn fixed_frame_time(&self) -> Option<f32> { None }
+}
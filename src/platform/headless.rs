@@ -0,0 +1,89 @@
+use crate::Key;
+use crate::platform::{Platform, PlatformResult};
+
+const HEADLESS_FRAME_TIME: f32 = 1.0 / 60.0;
+
+// mouse_pos is in window pixels, which are screen pixels times the pixel size
+#[derive(Debug, Clone, Default)]
+pub struct InputFrame {
+    pub keys: Vec<Key>,
+    pub mouse_pos: Option<(i32, i32)>,
+    pub mouse_buttons: [bool; 3],
+    pub mouse_wheel: i32
+}
+
+pub struct HeadlessPlatform {
+    size: (usize, usize),
+    frames_left: Option<u32>,
+    input: Box<dyn Iterator<Item = InputFrame>>,
+    current: InputFrame,
+    previous_keys: Vec<Key>
+}
+
+impl std::fmt::Debug for HeadlessPlatform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HeadlessPlatform")
+            .field("size", &self.size)
+            .field("frames_left", &self.frames_left)
+            .field("current", &self.current)
+            .finish()
+    }
+}
+
+impl HeadlessPlatform {
+    pub fn new<I>(frames: Option<u32>, input: I) -> Self
+    where
+        I: IntoIterator<Item = InputFrame>,
+        I::IntoIter: 'static
+    {
+        Self {
+            size: (0, 0),
+            frames_left: frames,
+            input: Box::new(input.into_iter()),
+            current: InputFrame::default(),
+            previous_keys: vec![]
+        }
+    }
+}
+
+impl Platform for HeadlessPlatform {
+    fn is_open(&self) -> bool { self.frames_left != Some(0) }
+    fn is_focused(&mut self) -> bool { true }
+    fn set_title(&mut self, _title: &str) {}
+
+    fn get_size(&self) -> (usize, usize) { self.size }
+
+    fn set_size(&mut self, width: usize, height: usize) -> PlatformResult {
+        self.size = (width, height);
+        Ok(())
+    }
+
+    fn set_resizable(&mut self, _resizable: bool) -> PlatformResult { Ok(()) }
+    fn set_fullscreen(&mut self, _fullscreen: bool) -> PlatformResult { Ok(()) }
+
+    fn poll_events(&mut self) {
+        let next = self.input.next().unwrap_or_default();
+        self.previous_keys = std::mem::replace(&mut self.current, next).keys;
+        if let Some(frames) = self.frames_left.as_mut() {
+            *frames = frames.saturating_sub(1);
+        }
+    }
+
+    fn present(&mut self, _buffer: &[u32], _width: usize, _height: usize) -> PlatformResult { Ok(()) }
+
+    fn get_keys(&self) -> Vec<Key> { self.current.keys.clone() }
+
+    fn get_keys_pressed(&self) -> Vec<Key> {
+        self.current.keys.iter().filter(|k| !self.previous_keys.contains(k)).copied().collect()
+    }
+
+    fn get_keys_released(&self) -> Vec<Key> {
+        self.previous_keys.iter().filter(|k| !self.current.keys.contains(k)).copied().collect()
+    }
+
+    fn get_mouse_pos(&self) -> Option<(f32, f32)> { self.current.mouse_pos.map(|(x, y)| (x as f32, y as f32)) }
+    fn get_mouse_down(&self, button: usize) -> bool { self.current.mouse_buttons.get(button).copied().unwrap_or(false) }
+    fn get_mouse_wheel(&self) -> i32 { self.current.mouse_wheel }
+
+    fn fixed_frame_time(&self) -> Option<f32> { Some(HEADLESS_FRAME_TIME) }
+}
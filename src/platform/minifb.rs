@@ -0,0 +1,214 @@
+use crate::{EngineConfig, Key};
+use crate::platform::{Platform, PlatformResult};
+
+const KEYMAP: [Key; ::minifb::Key::Count as usize] = [
+    Key::K0, Key::K1, Key::K2, Key::K3, Key::K4, Key::K5, Key::K6, Key::K7, Key::K8, Key::K9,
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K, Key::L,
+    Key::M, Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X,
+    Key::Y, Key::Z,
+    Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6,
+    Key::F7, Key::F8, Key::F9, Key::F10, Key::F11, Key::F12,
+    Key::None, Key::None, Key::None, // F13, F14, F15
+    Key::Down, Key::Left, Key::Right, Key::Up,
+    Key::Apostrophe, Key::BackQuote, Key::BackSlash, Key::Comma,
+    Key::Equal, Key::LeftBracket, Key::Minus, Key::Period,
+    Key::RightBracket, Key::Semicolon, Key::Slash,
+    Key::Back, Key::Delete, Key::End, Key::Return, Key::Escape,
+    Key::Home, Key::Insert, Key::Menu,
+    Key::PageDown, Key::PageUp, Key::Pause, Key::Space, Key::Tab,
+    Key::NumLock, Key::CapsLock, Key::ScrollLock,
+    Key::LeftShift, Key::RightShift, Key::LeftControl, Key::RightControl,
+    Key::NumPad0, Key::NumPad1, Key::NumPad2, Key::NumPad3, Key::NumPad4,
+    Key::NumPad5, Key::NumPad6, Key::NumPad7, Key::NumPad8, Key::NumPad9,
+    Key::NumPadDecimal, Key::NumPadDiv, Key::NumPadMul, Key::NumPadSub, Key::NumPadAdd,
+    Key::NumPadEnter, Key::LeftAlt, Key::RightAlt,
+    Key::LeftSystem, Key::RightSystem,
+    Key::None // Unknown
+];
+
+#[derive(Debug)]
+pub struct MinifbPlatform {
+    window: ::minifb::Window,
+    title: String,
+    options: ::minifb::WindowOptions,
+    windowed_size: Option<(usize, usize)>
+}
+
+impl MinifbPlatform {
+    pub fn new(title: &str, config: &EngineConfig) -> ::minifb::Result<Self> {
+        let options = ::minifb::WindowOptions {
+            borderless: config.is_borderless(),
+            title: !config.is_borderless(),
+            resize: config.is_resizable(),
+            scale: ::minifb::Scale::X1,
+            scale_mode: ::minifb::ScaleMode::Stretch,
+            topmost: config.is_topmost(),
+            transparency: config.is_transparent(),
+            none: false
+        };
+
+        Ok(Self {
+            window: Self::create_window(title, config.window_width(), config.window_height(), options)?,
+            title: title.to_string(),
+            options,
+            windowed_size: None
+        })
+    }
+
+    fn create_window(title: &str, width: usize, height: usize, options: ::minifb::WindowOptions) -> ::minifb::Result<::minifb::Window> {
+        let mut window = ::minifb::Window::new(title, width, height, options)?;
+        window.limit_update_rate(None);
+        Ok(window)
+    }
+
+    fn recreate_window(&mut self, width: usize, height: usize) -> PlatformResult {
+        self.window = match self.windowed_size {
+            Some(_) => {
+                let options = ::minifb::WindowOptions { borderless: true, title: false, resize: false, ..self.options };
+                let mut window = Self::create_window(&self.title, width, height, options)?;
+                window.set_position(0, 0);
+                window
+            }
+            None => Self::create_window(&self.title, width, height, self.options)?
+        };
+        Ok(())
+    }
+
+    fn map_keys(keys: Option<Vec<::minifb::Key>>) -> Vec<Key> {
+        keys.unwrap_or_default().into_iter().map(|k| KEYMAP[k as usize]).collect()
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "redox", windows)))]
+fn display_size() -> Option<(usize, usize)> {
+    let lib = x11_dl::xlib::Xlib::open().ok()?;
+    unsafe {
+        let display = (lib.XOpenDisplay)(std::ptr::null());
+        if display.is_null() {
+            return None
+        }
+        let screen = (lib.XDefaultScreen)(display);
+        let size = ((lib.XDisplayWidth)(display, screen), (lib.XDisplayHeight)(display, screen));
+        (lib.XCloseDisplay)(display);
+        Some((size.0.max(0) as usize, size.1.max(0) as usize))
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "redox", windows)))]
+fn display_refresh_rate() -> Option<f32> {
+    let lib = x11_dl::xlib::Xlib::open().ok()?;
+    let xrandr = x11_dl::xrandr::Xrandr::open().ok()?;
+    unsafe {
+        let display = (lib.XOpenDisplay)(std::ptr::null());
+        if display.is_null() {
+            return None
+        }
+        let info = (xrandr.XRRGetScreenInfo)(display, (lib.XDefaultRootWindow)(display));
+        let rate = if info.is_null() { 0 } else { (xrandr.XRRConfigCurrentRate)(info) };
+        if !info.is_null() {
+            (xrandr.XRRFreeScreenConfigInfo)(info);
+        }
+        (lib.XCloseDisplay)(display);
+        if rate > 0 { Some(rate as f32) } else { None }
+    }
+}
+
+#[cfg(windows)]
+fn display_size() -> Option<(usize, usize)> {
+    use winapi::um::winuser::{GetSystemMetrics, SM_CXSCREEN, SM_CYSCREEN};
+    let (w, h) = unsafe { (GetSystemMetrics(SM_CXSCREEN), GetSystemMetrics(SM_CYSCREEN)) };
+    if w > 0 && h > 0 { Some((w as usize, h as usize)) } else { None }
+}
+
+#[cfg(windows)]
+fn display_refresh_rate() -> Option<f32> {
+    use winapi::um::wingdi::{GetDeviceCaps, VREFRESH};
+    use winapi::um::winuser::{GetDC, ReleaseDC};
+    let rate = unsafe {
+        let dc = GetDC(std::ptr::null_mut());
+        let rate = GetDeviceCaps(dc, VREFRESH);
+        ReleaseDC(std::ptr::null_mut(), dc);
+        rate
+    };
+    // 0 and 1 both mean the hardware default
+    if rate > 1 { Some(rate as f32) } else { None }
+}
+
+#[cfg(any(target_os = "macos", target_os = "redox"))]
+fn display_size() -> Option<(usize, usize)> { None }
+
+#[cfg(any(target_os = "macos", target_os = "redox"))]
+fn display_refresh_rate() -> Option<f32> { None }
+
+impl Platform for MinifbPlatform {
+    fn is_open(&self) -> bool { self.window.is_open() }
+    fn is_focused(&mut self) -> bool { self.window.is_active() }
+    fn set_title(&mut self, title: &str) {
+        self.title = title.to_string();
+        self.window.set_title(title);
+    }
+
+    fn get_size(&self) -> (usize, usize) { self.window.get_size() }
+
+    // minifb can't resize a window, so it gets replaced with a new one
+    fn set_size(&mut self, width: usize, height: usize) -> PlatformResult {
+        if self.windowed_size.is_some() {
+            self.windowed_size = Some((width, height));
+        }
+        else if self.window.get_size() != (width, height) {
+            self.recreate_window(width, height)?;
+        }
+        Ok(())
+    }
+
+    fn set_resizable(&mut self, resizable: bool) -> PlatformResult {
+        if self.options.resize != resizable {
+            self.options.resize = resizable;
+            if self.windowed_size.is_none() {
+                let (width, height) = self.window.get_size();
+                self.recreate_window(width, height)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn set_fullscreen(&mut self, fullscreen: bool) -> PlatformResult {
+        match (fullscreen, self.windowed_size) {
+            (true, None) => {
+                let (width, height) = display_size().ok_or("fullscreen is not supported on this platform")?;
+                self.windowed_size = Some(self.window.get_size());
+                self.recreate_window(width, height)
+            }
+            (false, Some((width, height))) => {
+                self.windowed_size = None;
+                self.recreate_window(width, height)
+            }
+            _ => Ok(())
+        }
+    }
+
+    fn get_refresh_rate(&self) -> Option<f32> { display_refresh_rate() }
+
+    fn present(&mut self, buffer: &[u32], width: usize, height: usize) -> PlatformResult {
+        self.window.update_with_buffer(buffer, width, height)?;
+        Ok(())
+    }
+
+    fn get_keys(&self) -> Vec<Key> { Self::map_keys(self.window.get_keys()) }
+    fn get_keys_pressed(&self) -> Vec<Key> { Self::map_keys(self.window.get_keys_pressed(::minifb::KeyRepeat::No)) }
+    fn get_keys_released(&self) -> Vec<Key> { Self::map_keys(self.window.get_keys_released()) }
+
+    fn get_mouse_pos(&self) -> Option<(f32, f32)> { self.window.get_mouse_pos(::minifb::MouseMode::Discard) }
+
+    fn get_mouse_down(&self, button: usize) -> bool {
+        use ::minifb::MouseButton;
+        match button {
+            0 => self.window.get_mouse_down(MouseButton::Left),
+            1 => self.window.get_mouse_down(MouseButton::Right),
+            2 => self.window.get_mouse_down(MouseButton::Middle),
+            _ => false
+        }
+    }
+
+    fn get_mouse_wheel(&self) -> i32 { self.window.get_scroll_wheel().map_or(0, |(_, y)| y as i32) }
+}
@@ -0,0 +1,194 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+use image::{Delay, Frame, ImageError, RgbaImage};
+use image::gif::{GifEncoder, Repeat};
+
+use crate::error::PgeError;
+use crate::sprite::Sprite;
+
+// 1 is the best quality, 30 is the fastest
+const GIF_SPEED: i32 = 10;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RecordFormat {
+    Gif,
+    PngSequence
+}
+
+// made from the app through the engine, and carried out by the context when the frame is presented
+#[derive(Debug)]
+pub(crate) enum RecordingRequest {
+    Start(Box<Recorder>),
+    Stop
+}
+
+// The gif encoder only lets go of its writer by being dropped, which throws away any error
+// from writing the trailer, so the recorder keeps a handle of its own to flush and check.
+#[derive(Clone)]
+struct GifOutput(Rc<RefCell<(BufWriter<File>, Option<io::Error>)>>);
+
+impl GifOutput {
+    fn finish(&self) -> io::Result<()> {
+        let mut output = self.0.borrow_mut();
+        match output.1.take() {
+            Some(e) => Err(e),
+            None => output.0.flush()
+        }
+    }
+}
+
+impl Write for GifOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut output = self.0.borrow_mut();
+        match output.0.write(buf) {
+            Err(e) if output.1.is_none() => {
+                let copy = io::Error::new(e.kind(), e.to_string());
+                output.1 = Some(e);
+                Err(copy)
+            }
+            result => result
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> { self.0.borrow_mut().0.flush() }
+}
+
+pub struct Recorder {
+    file_name: String,
+    format: RecordFormat,
+    max_duration: Option<f32>,
+    frame_skip: u32,
+
+    encoder: Option<GifEncoder<GifOutput>>,
+    output: Option<GifOutput>,
+    pending: Option<(RgbaImage, f32)>,
+    frames_seen: u32,
+    frames_saved: u32,
+    time: f32,
+    last_elapsed: f32,
+    written_cs: u32
+}
+
+impl std::fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Recorder")
+            .field("file_name", &self.file_name)
+            .field("format", &self.format)
+            .field("max_duration", &self.max_duration)
+            .field("frame_skip", &self.frame_skip)
+            .field("frames_saved", &self.frames_saved)
+            .field("time", &self.time)
+            .finish()
+    }
+}
+
+impl Recorder {
+    pub fn new(file_name: &str, format: RecordFormat) -> Self {
+        Self {
+            file_name: file_name.to_string(),
+            format,
+            max_duration: None,
+            frame_skip: 0,
+
+            encoder: None,
+            output: None,
+            pending: None,
+            frames_seen: 0,
+            frames_saved: 0,
+            time: 0.0,
+            last_elapsed: 0.0,
+            written_cs: 0
+        }
+    }
+
+    pub fn max_duration(mut self, seconds: Option<f32>) -> Self { self.max_duration = seconds; self }
+    pub fn frame_skip(mut self, skip: u32) -> Self { self.frame_skip = skip; self }
+
+    pub fn get_format(&self) -> RecordFormat { self.format }
+    pub fn get_frames_saved(&self) -> u32 { self.frames_saved }
+    pub fn get_duration(&self) -> f32 { self.time }
+
+    pub(crate) fn begin(&mut self) -> Result<(), PgeError> {
+        if self.format == RecordFormat::Gif {
+            let file = File::create(&self.file_name).map_err(|e| PgeError::ImageSave(ImageError::IoError(e)))?;
+            let output = GifOutput(Rc::new(RefCell::new((BufWriter::new(file), None))));
+            let mut encoder = GifEncoder::new_with_speed(output.clone(), GIF_SPEED);
+            encoder.set_repeat(Repeat::Infinite).map_err(PgeError::ImageSave)?;
+            self.encoder = Some(encoder);
+            self.output = Some(output);
+        }
+        Ok(())
+    }
+
+    // returns false once the recording has run for its maximum duration
+    pub(crate) fn capture(&mut self, screen: &Sprite, elapsed_time: f32) -> Result<bool, PgeError> {
+        if self.frames_seen > 0 {
+            self.time += elapsed_time;
+        }
+        self.last_elapsed = elapsed_time;
+
+        if self.max_duration.map_or(false, |max| self.time > max) {
+            return Ok(false)
+        }
+
+        if self.frames_seen % self.frame_skip.saturating_add(1) == 0 {
+            let frame = screen.to_image_buffer();
+            match self.format {
+                RecordFormat::Gif => {
+                    if let Some((image, _)) = self.pending.take() {
+                        self._write_gif_frame(image, self.time)?;
+                    }
+                    self.pending = Some((frame, self.time));
+                }
+                RecordFormat::PngSequence => {
+                    frame.save(self._sequence_name(self.frames_saved)).map_err(PgeError::ImageSave)?;
+                    self.frames_saved += 1;
+                }
+            }
+        }
+        self.frames_seen += 1;
+
+        Ok(true)
+    }
+
+    pub(crate) fn finish(&mut self) -> Result<(), PgeError> {
+        // the last frame stays up for as long as it would have until the next capture
+        if let Some((image, start)) = self.pending.take() {
+            let shown = if self.time > start { self.time - start } else { self.last_elapsed * self.frame_skip.saturating_add(1) as f32 };
+            self._write_gif_frame(image, start + shown)?;
+        }
+        // dropping the encoder writes the trailer
+        self.encoder = None;
+        match self.output.take() {
+            Some(output) => output.finish().map_err(|e| PgeError::ImageSave(ImageError::IoError(e))),
+            None => Ok(())
+        }
+    }
+
+    // delays are stored in hundredths of a second, so they're rounded against the total
+    // time to stop the error building up over a long recording
+    fn _write_gif_frame(&mut self, image: RgbaImage, end_time: f32) -> Result<(), PgeError> {
+        let end_cs = (end_time * 100.0).round() as u32;
+        let delay = end_cs.saturating_sub(self.written_cs).max(1);
+        self.written_cs += delay;
+
+        if let Some(encoder) = self.encoder.as_mut() {
+            let frame = Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(delay * 10, 1));
+            encoder.encode_frame(frame).map_err(PgeError::ImageSave)?;
+            self.frames_saved += 1;
+        }
+        Ok(())
+    }
+
+    // "clip.png" becomes "clip_0000.png", "clip_0001.png" and so on
+    fn _sequence_name(&self, index: u32) -> String {
+        let path = Path::new(&self.file_name);
+        let stem = path.file_stem().map_or_else(String::new, |s| s.to_string_lossy().into_owned());
+        let extension = path.extension().map_or_else(|| "png".to_string(), |e| e.to_string_lossy().into_owned());
+        path.with_file_name(format!("{}_{:04}.{}", stem, index, extension)).to_string_lossy().into_owned()
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+use std::convert::{TryFrom, TryInto};
+use std::fs;
+use std::io::{self, ErrorKind};
+
+use crate::error::PgeError;
+
+// Same layout as olc::ResourcePack, so packs can be shared with the C++ version:
+//   u32 index length, then the index itself (xor'd with the key),
+//   then the contents of every file back to back.
+// The index is a u32 file count, then for each file a u32 name length, the name,
+// the u32 file size, and the u32 offset of the file from the start of the pack.
+// Everything is little endian.
+#[derive(Debug, Clone, Default)]
+pub struct ResourcePack {
+    files: BTreeMap<String, Vec<u8>>
+}
+
+impl ResourcePack {
+    pub fn new() -> Self { Self::default() }
+
+    // the file is stored under the path it was added with, using / as the separator
+    pub fn add_file(&mut self, file_name: &str) -> Result<(), PgeError> {
+        let data = fs::read(file_name).map_err(PgeError::ResourcePack)?;
+        self.files.insert(make_posix(file_name), data);
+        Ok(())
+    }
+
+    pub fn load_pack(file_name: &str, key: &str) -> Result<Self, PgeError> {
+        let pack = fs::read(file_name).map_err(PgeError::ResourcePack)?;
+        Self::from_bytes(&pack, key).map_err(PgeError::ResourcePack)
+    }
+
+    pub fn save_pack(&self, file_name: &str, key: &str) -> Result<(), PgeError> {
+        let pack = self.to_bytes(key).map_err(PgeError::ResourcePack)?;
+        fs::write(file_name, pack).map_err(PgeError::ResourcePack)
+    }
+
+    pub fn get_file_buffer(&self, file_name: &str) -> Option<&[u8]> {
+        self.files.get(&make_posix(file_name)).map(|data| data.as_slice())
+    }
+
+    pub fn contains(&self, file_name: &str) -> bool { self.files.contains_key(&make_posix(file_name)) }
+    pub fn file_names(&self) -> impl Iterator<Item = &str> { self.files.keys().map(|name| name.as_str()) }
+
+    fn to_bytes(&self, key: &str) -> io::Result<Vec<u8>> {
+        let index_size = 4 + self.files.keys().map(|name| 12 + name.len()).sum::<usize>();
+        let mut offset = 4 + index_size;
+
+        let mut index = Vec::with_capacity(index_size);
+        index.extend_from_slice(&to_u32(self.files.len())?.to_le_bytes());
+        for (name, data) in &self.files {
+            index.extend_from_slice(&to_u32(name.len())?.to_le_bytes());
+            index.extend_from_slice(name.as_bytes());
+            index.extend_from_slice(&to_u32(data.len())?.to_le_bytes());
+            index.extend_from_slice(&to_u32(offset)?.to_le_bytes());
+            offset = offset.checked_add(data.len()).ok_or_else(too_large)?;
+        }
+        to_u32(offset)?;
+
+        let mut pack = Vec::with_capacity(offset);
+        pack.extend_from_slice(&to_u32(index.len())?.to_le_bytes());
+        pack.extend(scramble(&index, key));
+        for data in self.files.values() {
+            pack.extend_from_slice(data);
+        }
+        Ok(pack)
+    }
+
+    fn from_bytes(pack: &[u8], key: &str) -> io::Result<Self> {
+        let index_len = read_u32(pack, 0)? as usize;
+        let index = scramble(pack.get(4..4 + index_len).ok_or_else(corrupt)?, key);
+
+        let mut files = BTreeMap::new();
+        let mut pos = 4;
+        for _ in 0..read_u32(&index, 0)? {
+            let name_len = read_u32(&index, pos)? as usize;
+            let name = index.get(pos + 4..pos + 4 + name_len).ok_or_else(corrupt)?;
+            let name = String::from_utf8(name.to_vec()).map_err(|_| corrupt())?;
+            pos += 4 + name_len;
+
+            let size = read_u32(&index, pos)? as usize;
+            let offset = read_u32(&index, pos + 4)? as usize;
+            pos += 8;
+
+            let data = pack.get(offset..offset + size).ok_or_else(corrupt)?;
+            files.insert(name, data.to_vec());
+        }
+
+        Ok(Self { files })
+    }
+}
+
+// a wrong key shows up as a corrupt index
+fn corrupt() -> io::Error { io::Error::new(ErrorKind::InvalidData, "corrupt resource pack or wrong key") }
+
+// sizes and offsets are all 32 bit, so a pack can't go past 4 GiB
+fn too_large() -> io::Error { io::Error::new(ErrorKind::InvalidInput, "resource pack would be larger than 4 GiB") }
+
+fn to_u32(n: usize) -> io::Result<u32> { u32::try_from(n).map_err(|_| too_large()) }
+
+fn read_u32(bytes: &[u8], pos: usize) -> io::Result<u32> {
+    let b = bytes.get(pos..pos + 4).ok_or_else(corrupt)?;
+    Ok(u32::from_le_bytes(b.try_into().unwrap()))
+}
+
+fn scramble(data: &[u8], key: &str) -> Vec<u8> {
+    let key = key.as_bytes();
+    if key.is_empty() {
+        return data.to_vec()
+    }
+    data.iter().zip(key.iter().cycle()).map(|(d, k)| d ^ k).collect()
+}
+
+fn make_posix(path: &str) -> String { path.replace('\\', "/") }
@@ -1,19 +1,48 @@
 use std::rc::Rc;
 use std::cell::RefCell;
 
-use crate::pixel::Pixel;
+use crate::error::PgeError;
+use crate::pixel::{Pixel, pixel_lerp, blend_alpha};
+use crate::resource_pack::ResourcePack;
 
-use image::Pixel as i_just_need_the_trait;
+use image::RgbaImage;
 use image::io::Reader as ImageReader;
 
-#[allow(dead_code)]
-#[derive(Debug)]
-enum SpriteMode {
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SpriteMode {
     Normal,
-    Periodic
+    Periodic,
+    Clamp,
+    Mirror
 }
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SpriteFlip {
+    None,
+    Horizontal,
+    Vertical,
+    Both
+}
+
+impl SpriteFlip {
+    // where a pixel in a w by h area comes from once it's flipped
+    pub(crate) fn apply(self, x: i32, y: i32, w: i32, h: i32) -> (i32, i32) {
+        match self {
+            SpriteFlip::None => (x, y),
+            SpriteFlip::Horizontal => (w - 1 - x, y),
+            SpriteFlip::Vertical => (x, h - 1 - y),
+            SpriteFlip::Both => (w - 1 - x, h - 1 - y)
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SpriteFilter {
+    Nearest,
+    Bilinear
+}
+
+#[derive(Debug, Clone)]
 pub struct Sprite {
     width: u32,
     height: u32,
@@ -45,22 +74,99 @@ impl Sprite {
     }
 
     pub fn load_file(file_name: &str) -> Self {
-        //panic!("load_file not implemented");
+        Sprite::try_load_file(file_name).unwrap_or_else(|e| {
+            panic!("{}: {}", file_name, e)
+        })
+    }
+
+    pub fn try_load_file(file_name: &str) -> Result<Self, PgeError> {
+        let img = ImageReader::open(file_name).map_err(image::ImageError::IoError)?.decode()?;
+        Ok(Sprite::from_image_buffer(&img.into_rgba8()))
+    }
+
+    // the format is guessed from the data, handy with include_bytes!()
+    pub fn from_memory(bytes: &[u8]) -> Result<Self, PgeError> {
+        let img = image::load_from_memory(bytes)?;
+        Ok(Sprite::from_image_buffer(&img.into_rgba8()))
+    }
+
+    pub fn load_from_pack(pack: &ResourcePack, file_name: &str) -> Self {
+        Sprite::try_load_from_pack(pack, file_name).unwrap_or_else(|e| {
+            panic!("{}: {}", file_name, e)
+        })
+    }
 
-        let img = ImageReader::open(file_name).unwrap().decode().unwrap().into_bgra8();
+    pub fn try_load_from_pack(pack: &ResourcePack, file_name: &str) -> Result<Self, PgeError> {
+        let bytes = pack.get_file_buffer(file_name).ok_or_else(|| {
+            PgeError::ResourcePack(std::io::Error::new(std::io::ErrorKind::NotFound, "file is not in the resource pack"))
+        })?;
+        Sprite::from_memory(bytes)
+    }
+
+    // the format comes from the file extension
+    pub fn save_file(&self, file_name: &str) -> Result<(), PgeError> {
+        self.to_image_buffer().save(file_name).map_err(PgeError::ImageSave)
+    }
+
+    // reuses the allocation, unlike clone()
+    pub(crate) fn copy_from(&mut self, other: &Sprite) {
+        self.width = other.width;
+        self.height = other.height;
+        self.data.clone_from(&other.data);
+        self.mode_sample = other.mode_sample;
+    }
 
+    pub fn from_image_buffer(img: &RgbaImage) -> Self {
         let mut sprite = Sprite::new(img.width(), img.height());
 
-        for (i, p) in img.pixels().enumerate() {
-            let p = p.channels();
-            sprite.data[i] = Pixel::rgba(p[2], p[1], p[0], p[3]).into();
+        for (d, p) in sprite.data.iter_mut().zip(img.pixels()) {
+            let [r, g, b, a] = p.0;
+            *d = Pixel::rgba(r, g, b, a).into();
         }
 
         sprite
     }
 
+    pub fn to_image_buffer(&self) -> RgbaImage {
+        RgbaImage::from_fn(self.width, self.height, |x, y| {
+            let p = Pixel::from(self.data[(y * self.width + x) as usize]);
+            image::Rgba([p.r, p.g, p.b, p.a])
+        })
+    }
+
     pub fn into_ref(self) -> SpriteRef { Rc::new(RefCell::new(self)) }
 
+    pub fn flipped(&self, flip: SpriteFlip) -> Sprite {
+        let (w, h) = (self.width as i32, self.height as i32);
+        self._remap(self.width, self.height, |x, y| flip.apply(x, y, w, h))
+    }
+
+    // rotations are clockwise
+    pub fn rotated_90(&self) -> Sprite {
+        let h = self.height as i32;
+        self._remap(self.height, self.width, |x, y| (y, h - 1 - x))
+    }
+
+    pub fn rotated_180(&self) -> Sprite { self.flipped(SpriteFlip::Both) }
+
+    pub fn rotated_270(&self) -> Sprite {
+        let w = self.width as i32;
+        self._remap(self.height, self.width, |x, y| (w - 1 - y, x))
+    }
+
+    // builds a new w by h sprite, with each pixel copied from wherever source says in this one
+    fn _remap(&self, w: u32, h: u32, source: impl Fn(i32, i32) -> (i32, i32)) -> Sprite {
+        let mut sprite = Sprite::new(w, h);
+        sprite.mode_sample = self.mode_sample;
+        for y in 0..h as i32 {
+            for x in 0..w as i32 {
+                let (sx, sy) = source(x, y);
+                sprite.data[(y as u32 * w + x as u32) as usize] = self.data[(sy as u32 * self.width + sx as u32) as usize];
+            }
+        }
+        sprite
+    }
+
     pub fn width(&self) -> u32 { self.width }
     pub fn height(&self) -> u32 { self.height }
 
@@ -68,21 +174,38 @@ impl Sprite {
         &self.data
     }
 
+    pub fn set_sample_mode(&mut self, mode: SpriteMode) { self.mode_sample = mode; }
+    pub fn get_sample_mode(&self) -> SpriteMode { self.mode_sample }
+
     pub fn get_pixel(&self, x: i32, y: i32) -> Pixel {
-        match self.mode_sample {
+        let (w, h) = (self.width as i32, self.height as i32);
+        if w == 0 || h == 0 {
+            return Pixel::default()
+        }
+
+        let mirror = |i: i32, n: i32| {
+            let i = i.rem_euclid(2 * n);
+            if i < n { i } else { 2 * n - 1 - i }
+        };
+
+        let (x, y) = match self.mode_sample {
             SpriteMode::Normal => {
-                if x >= 0 && x < self.width as i32 && y >= 0 && y < self.height as i32 {
-                    Pixel::from(self.data[y as usize * self.width as usize + x as usize])
-                }
-                else {
-                    Pixel::default()
+                if x < 0 || x >= w || y < 0 || y >= h {
+                    return Pixel::default()
                 }
+                (x, y)
             }
-            SpriteMode::Periodic => {
-                let index = (y % self.height as i32).abs() as usize * self.width as usize + (x % self.width as i32).abs() as usize;
-                Pixel::from(self.data[index])
-            }
-        }
+            SpriteMode::Periodic => (x.rem_euclid(w), y.rem_euclid(h)),
+            SpriteMode::Clamp => (x.max(0).min(w - 1), y.max(0).min(h - 1)),
+            SpriteMode::Mirror => (mirror(x, w), mirror(y, h))
+        };
+
+        Pixel::from(self.data[y as usize * self.width as usize + x as usize])
+    }
+
+    pub fn clear(&mut self, p: Pixel) {
+        let p = p.into();
+        self.data.fill(p);
     }
 
     pub fn set_pixel(&mut self, x: i32, y: i32, p: Pixel) -> bool {
@@ -94,11 +217,118 @@ impl Sprite {
         false
     }
 
-    pub fn sample(_x: f32, _y: f32) -> Pixel {
-        unimplemented!()
+    // The rectangle functions below clip to the sprite first, then work a row at a time.
+    // Blits also skip anything outside of src, rather than reading it through the sample mode.
+
+    pub fn fill_rect(&mut self, x: i32, y: i32, w: u32, h: u32, p: Pixel) {
+        let p = p.into();
+        self._for_rows(x, y, w, h, |row| row.fill(p));
+    }
+
+    // what PixelMode::Alpha would do with a rectangle of p
+    pub fn blend_rect(&mut self, x: i32, y: i32, w: u32, h: u32, p: Pixel, blend: f32) {
+        self._for_rows(x, y, w, h, |row| {
+            for d in row.iter_mut() {
+                *d = blend_alpha(p, Pixel::from(*d), blend).into();
+            }
+        });
+    }
+
+    pub fn blit(&mut self, x: i32, y: i32, src: &Sprite, ox: i32, oy: i32, w: u32, h: u32) {
+        self._for_blit_rows(x, y, src, ox, oy, w, h, |dst, src| dst.copy_from_slice(src));
+    }
+
+    // only copies fully opaque pixels, like PixelMode::Mask
+    pub fn blit_masked(&mut self, x: i32, y: i32, src: &Sprite, ox: i32, oy: i32, w: u32, h: u32) {
+        self._for_blit_rows(x, y, src, ox, oy, w, h, |dst, src| {
+            for (d, &s) in dst.iter_mut().zip(src) {
+                if s >> 24 == 0xff {
+                    *d = s;
+                }
+            }
+        });
+    }
+
+    // blends like PixelMode::Alpha
+    pub fn blit_alpha(&mut self, x: i32, y: i32, src: &Sprite, ox: i32, oy: i32, w: u32, h: u32, blend: f32) {
+        self._for_blit_rows(x, y, src, ox, oy, w, h, |dst, src| {
+            for (d, &s) in dst.iter_mut().zip(src) {
+                *d = blend_alpha(Pixel::from(s), Pixel::from(*d), blend).into();
+            }
+        });
+    }
+
+    fn _for_rows(&mut self, x: i32, y: i32, w: u32, h: u32, mut f: impl FnMut(&mut [u32])) {
+        let x0 = (x as i64).max(0);
+        let y0 = (y as i64).max(0);
+        let x1 = (x as i64 + w as i64).min(self.width as i64);
+        let y1 = (y as i64 + h as i64).min(self.height as i64);
+        if x0 >= x1 || y0 >= y1 {
+            return
+        }
+
+        let stride = self.width as usize;
+        for row in self.data.chunks_exact_mut(stride).take(y1 as usize).skip(y0 as usize) {
+            f(&mut row[x0 as usize..x1 as usize]);
+        }
+    }
+
+    fn _for_blit_rows(&mut self, x: i32, y: i32, src: &Sprite, ox: i32, oy: i32, w: u32, h: u32, mut f: impl FnMut(&mut [u32], &[u32])) {
+        // move the top left corner until it's inside both sprites, then trim the size to fit
+        let (mut x, mut y, mut ox, mut oy) = (x as i64, y as i64, ox as i64, oy as i64);
+        let dx = (-x).max(-ox).max(0);
+        let dy = (-y).max(-oy).max(0);
+        x += dx; ox += dx;
+        y += dy; oy += dy;
+        let w = (w as i64 - dx).min(self.width as i64 - x).min(src.width as i64 - ox);
+        let h = (h as i64 - dy).min(self.height as i64 - y).min(src.height as i64 - oy);
+        if w <= 0 || h <= 0 {
+            return
+        }
+
+        let (x, y, ox, oy, w) = (x as usize, y as usize, ox as usize, oy as usize, w as usize);
+        let (stride, src_stride) = (self.width as usize, src.width as usize);
+        for row in 0..h as usize {
+            let dst = &mut self.data[(y + row) * stride + x..][..w];
+            let src = &src.data[(oy + row) * src_stride + ox..][..w];
+            f(dst, src);
+        }
     }
 
-    pub fn sample_bl(_u: f32, _v: f32) -> Pixel {
-        unimplemented!()
+    // u and v go from 0 to 1 across the sprite, what happens outside that depends on the sample mode
+    pub fn sample(&self, u: f32, v: f32) -> Pixel {
+        let mut x = (u * self.width as f32).floor() as i32;
+        let mut y = (v * self.height as f32).floor() as i32;
+
+        // like the original, the far edge still belongs to the sprite
+        if self.mode_sample == SpriteMode::Normal {
+            if u == 1.0 { x -= 1; }
+            if v == 1.0 { y -= 1; }
+        }
+
+        self.get_pixel(x, y)
+    }
+
+    pub fn sample_bl(&self, u: f32, v: f32) -> Pixel {
+        let u = u * self.width as f32 - 0.5;
+        let v = v * self.height as f32 - 0.5;
+
+        let x = u.floor() as i32;
+        let y = v.floor() as i32;
+        let u_ratio = u - x as f32;
+        let v_ratio = v - y as f32;
+
+        // normal sprites blend with their own edge instead of the empty pixels past it
+        let (x0, y0, x1, y1) = match self.mode_sample {
+            SpriteMode::Normal => {
+                let (w, h) = (self.width as i32 - 1, self.height as i32 - 1);
+                (x.max(0).min(w), y.max(0).min(h), (x + 1).max(0).min(w), (y + 1).max(0).min(h))
+            }
+            _ => (x, y, x + 1, y + 1)
+        };
+
+        let top = pixel_lerp(self.get_pixel(x0, y0), self.get_pixel(x1, y0), u_ratio);
+        let bottom = pixel_lerp(self.get_pixel(x0, y1), self.get_pixel(x1, y1), u_ratio);
+        pixel_lerp(top, bottom, v_ratio)
     }
 }
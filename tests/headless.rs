@@ -0,0 +1,764 @@
+use olc_pge as olc;
+use olc::{InputFrame, Key, Pixel, PixelMode, Sprite, SpriteRef};
+
+// Runs a closure once per frame on a headless engine, and counts the frames and fixed updates it ran for.
+struct Script<F: FnMut(&mut olc::PixelGameEngine, u32) -> bool> {
+    frame: u32,
+    fixed_updates: u32,
+    update: F
+}
+
+impl<F: FnMut(&mut olc::PixelGameEngine, u32) -> bool> olc::PGEApplication for Script<F> {
+    const APP_NAME: &'static str = "Headless Test";
+
+    fn on_user_update(&mut self, pge: &mut olc::PixelGameEngine, elapsed_time: f32) -> bool {
+        assert_eq!(elapsed_time, 1.0 / 60.0);
+        let keep_going = (self.update)(pge, self.frame);
+        self.frame += 1;
+        keep_going
+    }
+
+    fn on_fixed_update(&mut self, pge: &mut olc::PixelGameEngine, fixed_time: f32) -> bool {
+        assert_eq!(fixed_time, pge.get_fixed_step());
+        self.fixed_updates += 1;
+        true
+    }
+}
+
+fn script<F: FnMut(&mut olc::PixelGameEngine, u32) -> bool>(update: F) -> Script<F> {
+    Script { frame: 0, fixed_updates: 0, update }
+}
+
+// draws a single frame and hands back the default draw target
+fn draw(w: usize, h: usize, mut f: impl FnMut(&mut olc::PixelGameEngine)) -> SpriteRef {
+    let mut context = olc::PixelGameEngine::construct_headless(script(|pge, _| { f(pge); false }), w, h, Some(1), vec![]);
+    context.start().unwrap();
+    let screen = context.engine().get_draw_target();
+    screen
+}
+
+// a sprite where every pixel is different, so it's easy to tell where each one ended up
+fn numbered(w: u32, h: u32) -> Sprite {
+    let mut sprite = Sprite::new(w, h);
+    for y in 0..h as i32 {
+        for x in 0..w as i32 {
+            sprite.set_pixel(x, y, Pixel::rgb(x as u8, y as u8, 0));
+        }
+    }
+    sprite
+}
+
+#[test]
+fn runs_for_the_frame_count() {
+    let mut context = olc::PixelGameEngine::construct_headless(script(|_, _| true), 8, 8, Some(10), vec![]);
+    context.start().unwrap();
+    assert_eq!(context.app().frame, 10);
+    assert_eq!(context.app().fixed_updates, 10);
+}
+
+#[test]
+fn stops_when_the_app_does() {
+    let mut context = olc::PixelGameEngine::construct_headless(script(|_, frame| frame < 3), 8, 8, None, vec![]);
+    context.start().unwrap();
+    assert_eq!(context.app().frame, 4);
+}
+
+#[test]
+fn key_edges() {
+    let input = vec![
+        InputFrame::default(),
+        InputFrame { keys: vec![Key::Space], ..Default::default() },
+        InputFrame { keys: vec![Key::Space, Key::A], ..Default::default() },
+        InputFrame { keys: vec![Key::A], ..Default::default() },
+        InputFrame::default()
+    ];
+    let mut log = vec![];
+    let app = script(|pge, _| {
+        let (space, a) = (pge.get_key(Key::Space), pge.get_key(Key::A));
+        log.push(((space.pressed, space.held, space.released), (a.pressed, a.held, a.released)));
+        true
+    });
+    let mut context = olc::PixelGameEngine::construct_headless(app, 8, 8, Some(5), input);
+    context.start().unwrap();
+    drop(context);
+
+    assert_eq!(log, vec![
+        ((false, false, false), (false, false, false)),
+        ((true, true, false), (false, false, false)),
+        ((false, true, false), (true, true, false)),
+        ((false, false, true), (false, true, false)),
+        ((false, false, false), (false, false, true))
+    ]);
+}
+
+#[test]
+fn mouse_edges_and_position() {
+    let input = vec![
+        InputFrame { mouse_pos: Some((3, 4)), ..Default::default() },
+        InputFrame { mouse_buttons: [true, false, false], ..Default::default() },
+        InputFrame { mouse_buttons: [true, false, true], mouse_wheel: 120, ..Default::default() },
+        InputFrame { mouse_buttons: [false, false, true], mouse_pos: Some((7, 1)), ..Default::default() }
+    ];
+    let mut log = vec![];
+    let app = script(|pge, _| {
+        let (left, middle) = (pge.get_mouse(0), pge.get_mouse(2));
+        log.push((pge.get_mouse_x(), pge.get_mouse_y(), pge.get_mouse_wheel(),
+            (left.pressed, left.held, left.released), (middle.pressed, middle.held, middle.released)));
+        true
+    });
+    let mut context = olc::PixelGameEngine::construct_headless(app, 8, 8, Some(4), input);
+    context.start().unwrap();
+    drop(context);
+
+    // a button is pressed for the first frame it's down, and held after that
+    assert_eq!(log, vec![
+        (3, 4, 0, (false, false, false), (false, false, false)),
+        (3, 4, 0, (true, false, false), (false, false, false)),
+        (3, 4, 120, (false, true, false), (true, false, false)),
+        (7, 1, 0, (false, false, true), (false, true, false))
+    ]);
+}
+
+#[test]
+fn draws_into_the_screen() {
+    let screen = draw(6, 4, |pge| {
+        pge.clear(olc::BLUE);
+        pge.fill_rect(1, 1, 2, 2, olc::RED);
+        pge.draw(5, 3, olc::GREEN);
+        pge.draw(6, 0, olc::GREEN);
+    });
+    let screen = screen.borrow();
+    assert_eq!((screen.width(), screen.height()), (6, 4));
+    assert_eq!(screen.get_pixel(0, 0), olc::BLUE);
+    assert_eq!(screen.get_pixel(1, 1), olc::RED);
+    assert_eq!(screen.get_pixel(2, 2), olc::RED);
+    assert_eq!(screen.get_pixel(3, 2), olc::BLUE);
+    assert_eq!(screen.get_pixel(5, 3), olc::GREEN);
+    // off the edge is ignored rather than wrapping round
+    assert_eq!(screen.get_pixel(0, 1), olc::BLUE);
+}
+
+#[test]
+fn set_screen_size_reallocates_the_screen() {
+    let mut sizes = vec![];
+    let app = script(|pge, frame| {
+        let target = pge.get_draw_target();
+        sizes.push((pge.screen_width(), pge.screen_height(), target.borrow().width(), target.borrow().height()));
+        if frame == 0 {
+            pge.set_screen_size(12, 2).unwrap();
+            // the draw target is resized in place, so references to it stay good
+            assert_eq!(target.borrow().width(), 12);
+            pge.draw(11, 1, olc::RED);
+        }
+        true
+    });
+    let mut context = olc::PixelGameEngine::construct_headless(app, 8, 8, Some(2), vec![]);
+    context.start().unwrap();
+    assert_eq!(context.engine().get_draw_target().borrow().get_pixel(11, 1), olc::RED);
+    drop(context);
+
+    assert_eq!(sizes, vec![(8, 8, 8, 8), (12, 2, 12, 2)]);
+}
+
+#[test]
+fn headless_runs_are_not_paced() {
+    let app = script(|pge, frame| {
+        if frame == 0 {
+            pge.set_frame_cap(Some(1.0));
+            pge.set_vsync(true);
+        }
+        true
+    });
+    let mut context = olc::PixelGameEngine::construct_headless(app, 8, 8, Some(30), vec![]);
+    let start = std::time::Instant::now();
+    context.start().unwrap();
+    assert!(start.elapsed() < std::time::Duration::from_secs(5));
+}
+
+#[test]
+fn fixed_updates_follow_the_fixed_step() {
+    let app = script(|pge, frame| {
+        if frame == 0 {
+            pge.set_fixed_step(1.0 / 30.0);
+        }
+        true
+    });
+    let mut context = olc::PixelGameEngine::construct_headless(app, 8, 8, Some(10), vec![]);
+    context.start().unwrap();
+    // the first frame's step already ran at 1/60 before the app changed it
+    assert_eq!(context.app().fixed_updates, 1 + 9 / 2);
+}
+
+#[test]
+fn fixed_updates_catch_up_to_a_limit() {
+    let mut context = olc::PixelGameEngine::construct_headless(script(|_, _| true), 8, 8, Some(3), vec![]);
+    context.engine_mut().set_fixed_step(1.0 / 600.0);
+    context.engine_mut().set_max_fixed_steps(3);
+    context.start().unwrap();
+    // the other 7 steps each frame are dropped rather than piling up
+    assert_eq!(context.app().fixed_updates, 9);
+}
+
+#[test]
+fn fixed_alpha_is_the_time_left_over() {
+    let mut alphas = vec![];
+    let app = script(|pge, _| {
+        alphas.push(pge.get_fixed_alpha());
+        true
+    });
+    let mut context = olc::PixelGameEngine::construct_headless(app, 8, 8, Some(2), vec![]);
+    context.engine_mut().set_fixed_step(1.0 / 40.0);
+    context.start().unwrap();
+    assert_eq!(context.app().fixed_updates, 1);
+    drop(context);
+
+    // two thirds of a step after one frame, and a third of one after the step in the second
+    assert!((alphas[0] - 2.0 / 3.0).abs() < 1e-4, "{:?}", alphas);
+    assert!((alphas[1] - 1.0 / 3.0).abs() < 1e-4, "{:?}", alphas);
+}
+
+#[test]
+fn sample_modes() {
+    let mut sprite = numbered(3, 2);
+    assert_eq!(sprite.get_pixel(-1, 0), Pixel::rgb(0, 0, 0));
+    assert_eq!(sprite.get_pixel(-1, 0).a, 255);
+    assert_eq!(sprite.get_pixel(3, 0), Pixel::default());
+
+    sprite.set_sample_mode(olc::SpriteMode::Periodic);
+    assert_eq!(sprite.get_pixel(-1, 0), Pixel::rgb(2, 0, 0));
+    assert_eq!(sprite.get_pixel(4, -1), Pixel::rgb(1, 1, 0));
+
+    sprite.set_sample_mode(olc::SpriteMode::Clamp);
+    assert_eq!(sprite.get_pixel(-5, 7), Pixel::rgb(0, 1, 0));
+    assert_eq!(sprite.get_pixel(9, -2), Pixel::rgb(2, 0, 0));
+
+    sprite.set_sample_mode(olc::SpriteMode::Mirror);
+    assert_eq!(sprite.get_pixel(-1, 0), Pixel::rgb(0, 0, 0));
+    assert_eq!(sprite.get_pixel(-2, 0), Pixel::rgb(1, 0, 0));
+    assert_eq!(sprite.get_pixel(3, 2), Pixel::rgb(2, 1, 0));
+    assert_eq!(sprite.get_pixel(5, 3), Pixel::rgb(0, 0, 0));
+
+    sprite.set_sample_mode(olc::SpriteMode::Normal);
+    assert_eq!(sprite.sample(0.5, 0.5), Pixel::rgb(1, 1, 0));
+    assert_eq!(sprite.sample(1.0, 1.0), Pixel::rgb(2, 1, 0));
+    sprite.set_sample_mode(olc::SpriteMode::Periodic);
+    assert_eq!(sprite.sample(1.5, 0.0), Pixel::rgb(1, 0, 0));
+}
+
+#[test]
+fn bilinear_sampling() {
+    let mut sprite = Sprite::new(2, 1);
+    sprite.set_pixel(0, 0, Pixel::rgb(0, 0, 0));
+    sprite.set_pixel(1, 0, Pixel::rgb(200, 100, 0));
+
+    // halfway between the two pixel centres
+    assert_eq!(sprite.sample_bl(0.5, 0.5), Pixel::rgb(100, 50, 0));
+    // normal sprites hold their edge colour
+    assert_eq!(sprite.sample_bl(0.0, 0.5), Pixel::rgb(0, 0, 0));
+    // periodic ones blend with the far side
+    sprite.set_sample_mode(olc::SpriteMode::Periodic);
+    assert_eq!(sprite.sample_bl(0.0, 0.5), Pixel::rgb(100, 50, 0));
+}
+
+#[test]
+fn capture_screen_ignores_the_draw_target() {
+    let mut shot = None;
+    let app = script(|pge, _| {
+        pge.clear(olc::RED);
+        pge.set_draw_target(Some(Sprite::new(2, 2).into_ref()));
+        pge.clear(olc::GREEN);
+        shot = Some(pge.capture_screen());
+        false
+    });
+    let mut context = olc::PixelGameEngine::construct_headless(app, 3, 2, Some(1), vec![]);
+    context.start().unwrap();
+    drop(context);
+
+    let shot = shot.unwrap();
+    assert_eq!((shot.width(), shot.height()), (3, 2));
+    assert_eq!(shot.get_pixel(0, 0), olc::RED);
+    assert_eq!(shot.get_pixel(2, 1), olc::RED);
+}
+
+#[test]
+fn save_screenshot_scales_up() {
+    let dir = std::env::temp_dir().join(format!("olc-pge-screenshot-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let small = dir.join("small.png").to_string_lossy().into_owned();
+    let big = dir.join("big.png").to_string_lossy().into_owned();
+
+    let app = script(|pge, _| {
+        pge.clear(olc::BLUE);
+        pge.draw(2, 1, olc::RED);
+        pge.save_screenshot(&small, false).unwrap();
+        pge.save_screenshot(&big, true).unwrap();
+        false
+    });
+    let platform = olc::HeadlessPlatform::new(Some(1), vec![]);
+    let mut context = olc::PixelGameEngine::construct_with_platform(app, olc::EngineConfig::new(3, 2, 2, 3), platform);
+    context.start().unwrap();
+    drop(context);
+
+    let small = Sprite::load_file(&small);
+    assert_eq!((small.width(), small.height()), (3, 2));
+    assert_eq!(small.get_pixel(2, 1), olc::RED);
+
+    // each screen pixel becomes a 2x3 block
+    let big = Sprite::load_file(&big);
+    assert_eq!((big.width(), big.height()), (6, 6));
+    assert_eq!(big.get_pixel(3, 2), olc::BLUE);
+    assert_eq!(big.get_pixel(4, 3), olc::RED);
+    assert_eq!(big.get_pixel(5, 5), olc::RED);
+    assert_eq!(big.get_pixel(3, 5), olc::BLUE);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn custom_pixel_mode() {
+    let screen = draw(4, 2, |pge| {
+        // nothing set yet, so Custom draws like Normal
+        pge.set_pixel_mode(olc::PixelMode::Custom);
+        pge.clear(Pixel::rgb(100, 0, 0));
+
+        // a checkerboard that keeps the red already there and writes the position into blue
+        pge.set_pixel_mode_custom(|x, y, s, d| {
+            if (x + y) % 2 == 0 { s } else { Pixel::rgb(d.r, s.g, (x * 10 + y) as u8) }
+        });
+        pge.fill_rect(0, 0, 4, 2, Pixel::rgb(0, 50, 0));
+
+        // switching away and back picks the same function up again
+        pge.set_pixel_mode(olc::PixelMode::Normal);
+        pge.draw(3, 0, olc::WHITE);
+        pge.set_pixel_mode(olc::PixelMode::Custom);
+        pge.draw(2, 1, olc::WHITE);
+    });
+    let screen = screen.borrow();
+    assert_eq!(screen.get_pixel(0, 0), Pixel::rgb(0, 50, 0));
+    assert_eq!(screen.get_pixel(1, 0), Pixel::rgb(100, 50, 10));
+    assert_eq!(screen.get_pixel(0, 1), Pixel::rgb(100, 50, 1));
+    assert_eq!(screen.get_pixel(3, 1), Pixel::rgb(0, 50, 0));
+    assert_eq!(screen.get_pixel(3, 0), olc::WHITE);
+    assert_eq!(screen.get_pixel(2, 1), Pixel::rgb(100, 255, 21));
+}
+
+#[test]
+fn blend_modes() {
+    let dst = Pixel::rgb(100, 100, 100);
+    let src = Pixel::rgb(100, 50, 200);
+    let blend = |mode: PixelMode, src: Pixel| {
+        let screen = draw(1, 1, |pge| {
+            pge.clear(dst);
+            pge.set_pixel_mode(mode);
+            pge.draw(0, 0, src);
+        });
+        let p = screen.borrow().get_pixel(0, 0);
+        p
+    };
+
+    assert_eq!(blend(PixelMode::Additive, src), Pixel::rgb(200, 150, 255));
+    assert_eq!(blend(PixelMode::Multiply, src), Pixel::rgb(39, 20, 78));
+    assert_eq!(blend(PixelMode::Screen, src), Pixel::rgb(161, 130, 222));
+    assert_eq!(blend(PixelMode::Subtract, src), Pixel::rgb(0, 50, 0));
+    assert_eq!(blend(PixelMode::Min, src), Pixel::rgb(100, 50, 100));
+    assert_eq!(blend(PixelMode::Max, src), Pixel::rgb(100, 100, 200));
+
+    // the source's alpha fades the result back towards what was there
+    assert_eq!(blend(PixelMode::Additive, Pixel::rgba(100, 50, 200, 0)), dst);
+    assert_eq!(blend(PixelMode::Additive, Pixel::rgba(100, 50, 200, 51)), Pixel::rgb(120, 110, 131));
+
+    assert_eq!(blend(PixelMode::Over, Pixel::rgba(255, 0, 0, 51)), Pixel::rgb(131, 80, 80));
+    assert_eq!(blend(PixelMode::Alpha, Pixel::rgba(255, 0, 0, 51)), Pixel::rgb(131, 80, 80));
+    assert_eq!(blend(PixelMode::Mask, Pixel::rgba(255, 0, 0, 254)), dst);
+}
+
+#[test]
+fn over_onto_transparent() {
+    let screen = draw(1, 1, |pge| {
+        pge.set_pixel_mode(PixelMode::Over);
+        pge.draw(0, 0, Pixel::rgba(255, 0, 0, 51));
+    });
+    let p = screen.borrow().get_pixel(0, 0);
+    // Alpha would have made this opaque, Over keeps it see-through
+    assert_eq!(p, Pixel::rgba(255, 0, 0, 51));
+}
+
+#[test]
+fn quarter_turns() {
+    // 0 1 2
+    // 3 4 5
+    let mut sprite = Sprite::new(3, 2);
+    for i in 0..6 {
+        sprite.set_pixel(i % 3, i / 3, Pixel::rgb(i as u8, 0, 0));
+    }
+    let red = |s: &Sprite| (0..s.height() as i32).flat_map(|y| (0..s.width() as i32).map(move |x| (x, y))).map(|(x, y)| s.get_pixel(x, y).r).collect::<Vec<_>>();
+
+    // 3 0
+    // 4 1
+    // 5 2
+    let cw = sprite.rotated_90();
+    assert_eq!((cw.width(), cw.height()), (2, 3));
+    assert_eq!(red(&cw), vec![3, 0, 4, 1, 5, 2]);
+
+    // 2 5
+    // 1 4
+    // 0 3
+    let ccw = sprite.rotated_270();
+    assert_eq!((ccw.width(), ccw.height()), (2, 3));
+    assert_eq!(red(&ccw), vec![2, 5, 1, 4, 0, 3]);
+
+    assert_eq!(red(&sprite.rotated_180()), vec![5, 4, 3, 2, 1, 0]);
+    assert_eq!(red(&cw.rotated_90().rotated_90().rotated_90()), red(&sprite));
+}
+
+#[test]
+fn flips() {
+    let sprite = numbered(3, 2);
+    let h = sprite.flipped(olc::SpriteFlip::Horizontal);
+    assert_eq!(h.get_pixel(0, 0), Pixel::rgb(2, 0, 0));
+    assert_eq!(h.get_pixel(2, 1), Pixel::rgb(0, 1, 0));
+    let v = sprite.flipped(olc::SpriteFlip::Vertical);
+    assert_eq!(v.get_pixel(0, 0), Pixel::rgb(0, 1, 0));
+
+    let sprite = sprite.into_ref();
+    let screen = draw(8, 4, |pge| {
+        pge.draw_sprite_flip(0, 0, sprite.clone(), 1, olc::SpriteFlip::Both);
+        pge.draw_sprite_flip(3, 0, sprite.clone(), 2, olc::SpriteFlip::Horizontal);
+    });
+    let screen = screen.borrow();
+    assert_eq!(screen.get_pixel(0, 0), Pixel::rgb(2, 1, 0));
+    assert_eq!(screen.get_pixel(2, 1), Pixel::rgb(0, 0, 0));
+    assert_eq!(screen.get_pixel(3, 0), Pixel::rgb(2, 0, 0));
+    assert_eq!(screen.get_pixel(7, 3), Pixel::rgb(0, 1, 0));
+}
+
+#[test]
+fn rotated_drawing_matches_quarter_turns() {
+    let sprite = numbered(3, 3);
+    let turned = sprite.rotated_90();
+    let sprite = sprite.into_ref();
+    // turning about the middle of the sprite, with the middle on a pixel centre
+    let screen = draw(8, 8, |pge| {
+        pge.draw_sprite_rotated(olc::Vf2d::new(4.5, 4.5), sprite.clone(), std::f32::consts::FRAC_PI_2, olc::Vf2d::new(1.5, 1.5), 1.0, olc::SpriteFilter::Nearest);
+    });
+    let screen = screen.borrow();
+    for y in 0..3 {
+        for x in 0..3 {
+            assert_eq!(screen.get_pixel(3 + x, 3 + y), turned.get_pixel(x, y), "at {}, {}", x, y);
+        }
+    }
+    assert_eq!(screen.get_pixel(2, 3), Pixel::rgba(0, 0, 0, 0));
+    assert_eq!(screen.get_pixel(6, 5), Pixel::rgba(0, 0, 0, 0));
+}
+
+#[test]
+fn scaled_partial_sprite_draws_only_the_source_rectangle() {
+    let sheet = numbered(4, 4).into_ref();
+    let screen = draw(8, 8, |pge| {
+        pge.draw_partial_sprite_scaled(1, 1, sheet.clone(), 1, 2, 2, 1, 2);
+    });
+    let screen = screen.borrow();
+    assert_eq!(screen.get_pixel(1, 1), Pixel::rgb(1, 2, 0));
+    assert_eq!(screen.get_pixel(2, 2), Pixel::rgb(1, 2, 0));
+    assert_eq!(screen.get_pixel(3, 1), Pixel::rgb(2, 2, 0));
+    assert_eq!(screen.get_pixel(4, 2), Pixel::rgb(2, 2, 0));
+    assert_eq!(screen.get_pixel(5, 1), Pixel::rgba(0, 0, 0, 0));
+    assert_eq!(screen.get_pixel(1, 3), Pixel::rgba(0, 0, 0, 0));
+}
+
+#[test]
+fn draw_sprite_ex_stretches_and_shrinks() {
+    let sprite = numbered(4, 4).into_ref();
+    let screen = draw(8, 8, |pge| {
+        // twice as wide, the same height
+        pge.draw_sprite_ex(olc::Vf2d::new(0.0, 0.0), sprite.clone(), olc::Vi2d::new(0, 0), olc::Vi2d::new(2, 2), olc::Vf2d::new(2.0, 1.0), olc::SpriteFlip::None, olc::SpriteFilter::Nearest);
+        // half size, flipped across
+        pge.draw_sprite_ex(olc::Vf2d::new(5.0, 0.0), sprite.clone(), olc::Vi2d::new(0, 0), olc::Vi2d::new(4, 4), olc::Vf2d::new(0.5, 0.5), olc::SpriteFlip::Horizontal, olc::SpriteFilter::Nearest);
+    });
+    let screen = screen.borrow();
+    assert_eq!(screen.get_pixel(1, 0), Pixel::rgb(0, 0, 0));
+    assert_eq!(screen.get_pixel(2, 1), Pixel::rgb(1, 1, 0));
+    assert_eq!(screen.get_pixel(3, 1), Pixel::rgb(1, 1, 0));
+    assert_eq!(screen.get_pixel(0, 2), Pixel::rgba(0, 0, 0, 0));
+    // every other source pixel, right to left
+    assert_eq!(screen.get_pixel(5, 0), Pixel::rgb(3, 1, 0));
+    assert_eq!(screen.get_pixel(6, 1), Pixel::rgb(1, 3, 0));
+    assert_eq!(screen.get_pixel(7, 0), Pixel::rgba(0, 0, 0, 0));
+}
+
+#[test]
+fn bilinear_stays_inside_the_source_rectangle() {
+    let mut sheet = Sprite::new(4, 1);
+    for x in 0..4 {
+        sheet.set_pixel(x, 0, if x < 2 { olc::RED } else { olc::BLUE });
+    }
+    let sheet = sheet.into_ref();
+    let screen = draw(8, 2, |pge| {
+        pge.draw_sprite_ex(olc::Vf2d::new(0.0, 0.0), sheet.clone(), olc::Vi2d::new(0, 0), olc::Vi2d::new(2, 1), olc::Vf2d::new(3.5, 2.0), olc::SpriteFlip::None, olc::SpriteFilter::Bilinear);
+    });
+    let screen = screen.borrow();
+    for y in 0..2 {
+        for x in 0..7 {
+            assert_eq!(screen.get_pixel(x, y), olc::RED, "at {}, {}", x, y);
+        }
+    }
+}
+
+#[test]
+fn fill_rect_under_clip() {
+    let screen = draw(10, 10, |pge| {
+        pge.push_clip_rect(2, 3, 4, 5);
+        pge.fill_rect(0, 0, 10, 10, olc::RED);
+        pge.pop_clip_rect();
+    });
+    let screen = screen.borrow();
+    for y in 0..10 {
+        for x in 0..10 {
+            let inside = (2..6).contains(&x) && (3..8).contains(&y);
+            assert_eq!(screen.get_pixel(x, y), if inside { olc::RED } else { Pixel::rgba(0, 0, 0, 0) }, "at {}, {}", x, y);
+        }
+    }
+}
+
+#[test]
+fn draw_sprite_under_clip() {
+    let sprite = numbered(4, 4).into_ref();
+    let screen = draw(10, 10, |pge| {
+        pge.push_clip_rect(3, 3, 3, 2);
+        pge.draw_sprite(2, 2, sprite.clone());
+        pge.pop_clip_rect();
+    });
+    let screen = screen.borrow();
+    assert_eq!(screen.get_pixel(3, 3), Pixel::rgb(1, 1, 0));
+    assert_eq!(screen.get_pixel(5, 4), Pixel::rgb(3, 2, 0));
+    assert_eq!(screen.get_pixel(2, 2), Pixel::rgba(0, 0, 0, 0));
+    assert_eq!(screen.get_pixel(5, 5), Pixel::rgba(0, 0, 0, 0));
+
+    let screen = draw(10, 10, |pge| {
+        pge.push_clip_rect(0, 0, 5, 5);
+        pge.draw_sprite_scaled(1, 1, sprite.clone(), 2);
+        pge.pop_clip_rect();
+    });
+    let screen = screen.borrow();
+    assert_eq!(screen.get_pixel(1, 1), Pixel::rgb(0, 0, 0));
+    assert_eq!(screen.get_pixel(4, 3), Pixel::rgb(1, 1, 0));
+    assert_eq!(screen.get_pixel(5, 5), Pixel::rgba(0, 0, 0, 0));
+}
+
+#[test]
+fn clear_under_clip() {
+    let screen = draw(6, 6, |pge| {
+        pge.clear(olc::BLUE);
+        pge.push_clip_rect(1, 1, 2, 2);
+        pge.clear(olc::GREEN);
+        pge.pop_clip_rect();
+    });
+    let screen = screen.borrow();
+    assert_eq!(screen.get_pixel(1, 1), olc::GREEN);
+    assert_eq!(screen.get_pixel(2, 2), olc::GREEN);
+    assert_eq!(screen.get_pixel(0, 0), olc::BLUE);
+    assert_eq!(screen.get_pixel(3, 2), olc::BLUE);
+}
+
+#[test]
+fn clip_rects_nest() {
+    let screen = draw(10, 10, |pge| {
+        pge.push_clip_rect(0, 0, 6, 6);
+        pge.push_clip_rect(4, 4, 6, 6);
+        pge.fill_rect(0, 0, 10, 10, olc::RED);
+        pge.pop_clip_rect();
+        pge.draw(0, 0, olc::GREEN);
+        pge.pop_clip_rect();
+        pge.draw(9, 9, olc::GREEN);
+    });
+    let screen = screen.borrow();
+    assert_eq!(screen.get_pixel(4, 4), olc::RED);
+    assert_eq!(screen.get_pixel(5, 5), olc::RED);
+    assert_eq!(screen.get_pixel(6, 6), Pixel::rgba(0, 0, 0, 0));
+    assert_eq!(screen.get_pixel(0, 0), olc::GREEN);
+    assert_eq!(screen.get_pixel(9, 9), olc::GREEN);
+}
+
+#[test]
+fn everything_respects_the_clip() {
+    let sprite = numbered(6, 6).into_ref();
+    let screen = draw(12, 12, |pge| {
+        pge.clear(olc::BLACK);
+        pge.push_clip_rect(4, 4, 4, 4);
+        pge.clear(olc::DARK_GREY);
+        pge.draw_line(0, 0, 11, 11, olc::RED);
+        pge.draw_line(0, 6, 11, 5, olc::RED);
+        pge.draw_circle(6, 6, 4, olc::GREEN);
+        pge.fill_circle(2, 2, 4, olc::GREEN);
+        pge.draw_rect(1, 1, 10, 10, olc::BLUE);
+        pge.draw_triangle(0, 11, 11, 11, 6, 0, olc::YELLOW);
+        pge.fill_triangle(0, 0, 11, 0, 0, 11, olc::CYAN);
+        pge.draw_sprite_scaled(3, 3, sprite.clone(), 2);
+        pge.draw_sprite_ex(olc::Vf2d::new(0.0, 5.0), sprite.clone(), olc::Vi2d::new(0, 0), olc::Vi2d::new(6, 6), olc::Vf2d::new(1.5, 0.5), olc::SpriteFlip::None, olc::SpriteFilter::Nearest);
+        pge.draw_string(0, 2, "clip", olc::WHITE);
+        pge.pop_clip_rect();
+    });
+    let screen = screen.borrow();
+    for y in 0..12 {
+        for x in 0..12 {
+            if !(4..8).contains(&x) || !(4..8).contains(&y) {
+                assert_eq!(screen.get_pixel(x, y), olc::BLACK, "at {}, {}", x, y);
+            }
+        }
+    }
+    assert!((4..8).any(|y| (4..8).any(|x| screen.get_pixel(x, y) != olc::DARK_GREY)));
+}
+
+#[test]
+fn anti_aliased_coverage() {
+    let square = |offset: f32| [
+        olc::Vf2d::new(2.0 + offset, 2.0), olc::Vf2d::new(6.0 + offset, 2.0),
+        olc::Vf2d::new(6.0 + offset, 6.0), olc::Vf2d::new(2.0 + offset, 6.0)
+    ];
+
+    // a polygon on whole pixels covers them exactly, with no fringe
+    let screen = draw(8, 8, |pge| {
+        pge.clear(olc::BLACK);
+        pge.fill_polygon_aa(&square(0.0), olc::WHITE);
+    });
+    let screen = screen.borrow();
+    for y in 0..8 {
+        for x in 0..8 {
+            let inside = (2..6).contains(&x) && (2..6).contains(&y);
+            assert_eq!(screen.get_pixel(x, y).r, if inside { 255 } else { 0 }, "at {}, {}", x, y);
+        }
+    }
+
+    // moved half a pixel, the edge columns are half covered
+    let screen = draw(8, 8, |pge| {
+        pge.clear(olc::BLACK);
+        pge.fill_polygon_aa(&square(0.5), olc::WHITE);
+    });
+    let screen = screen.borrow();
+    assert_eq!(screen.get_pixel(2, 3).r, 128);
+    assert_eq!(screen.get_pixel(3, 3).r, 255);
+    assert_eq!(screen.get_pixel(6, 3).r, 128);
+
+    // a line through pixel centres is solid, with half strength ends
+    let screen = draw(8, 8, |pge| {
+        pge.clear(olc::BLACK);
+        pge.draw_line_aa(1.0, 3.0, 6.0, 3.0, olc::WHITE);
+    });
+    let screen = screen.borrow();
+    assert_eq!(screen.get_pixel(1, 3).r, 128);
+    assert_eq!(screen.get_pixel(3, 3).r, 255);
+    assert_eq!(screen.get_pixel(6, 3).r, 128);
+    assert_eq!(screen.get_pixel(3, 2).r, 0);
+
+    // between two rows, it's split evenly
+    let screen = draw(8, 8, |pge| {
+        pge.clear(olc::BLACK);
+        pge.draw_line_aa(1.0, 3.5, 6.0, 3.5, olc::WHITE);
+    });
+    let screen = screen.borrow();
+    assert_eq!(screen.get_pixel(3, 3).r, 128);
+    assert_eq!(screen.get_pixel(3, 4).r, 128);
+
+    // a circle is solid where it passes through a pixel centre, and the same on every side
+    let screen = draw(16, 16, |pge| {
+        pge.clear(olc::BLACK);
+        pge.draw_circle_aa(8.0, 8.0, 5.0, olc::WHITE);
+    });
+    let screen = screen.borrow();
+    for &(x, y) in &[(8, 3), (8, 13), (3, 8), (13, 8)] {
+        assert_eq!(screen.get_pixel(x, y).r, 255);
+    }
+    assert_eq!(screen.get_pixel(8, 8).r, 0);
+    assert_eq!(screen.get_pixel(5, 5), screen.get_pixel(11, 11));
+}
+
+#[test]
+fn screenshots_include_layers_and_decals() {
+    let mut blue = Sprite::new(2, 2);
+    blue.clear(olc::BLUE);
+    let decal = olc::Decal::new(blue.into_ref());
+    let mut shots = vec![];
+    let app = script(|pge, frame| {
+        if frame == 0 {
+            let layer = pge.create_layer();
+            pge.enable_layer(layer, true);
+            pge.set_draw_target_layer(layer);
+            pge.clear(olc::RED);
+            pge.set_draw_target(None);
+            pge.clear(olc::BLANK);
+        }
+        // the last frame's decal is gone, and this frame's shows up as soon as it's drawn
+        let before = pge.capture_screen().get_pixel(3, 3);
+        pge.draw_decal(olc::Vf2d::new(3.0, 3.0), &decal, olc::Vf2d::new(1.0, 1.0), olc::WHITE);
+        let after = pge.capture_screen();
+        shots.push((after.get_pixel(6, 6), before, after.get_pixel(3, 3)));
+        true
+    });
+    let mut context = olc::PixelGameEngine::construct_headless(app, 8, 8, Some(2), vec![]);
+    context.start().unwrap();
+
+    // after the run it's the last frame presented, decal and all
+    let shot = context.engine().capture_screen();
+    assert_eq!((shot.get_pixel(6, 6), shot.get_pixel(3, 3)), (olc::RED, olc::BLUE));
+    drop(context);
+
+    assert_eq!(shots, vec![(olc::RED, olc::RED, olc::BLUE), (olc::RED, olc::RED, olc::BLUE)]);
+}
+
+#[test]
+fn huge_shapes_are_clipped_before_drawing() {
+    let mut dot = Sprite::new(1, 1);
+    dot.set_pixel(0, 0, olc::BLUE);
+    let dot = dot.into_ref();
+
+    // none of these would finish if every off screen pixel were stepped through
+    let screen = draw(8, 8, |pge| {
+        pge.draw_sprite_scaled(-1000, -1000, dot.clone(), 1_000_000);
+        pge.draw_line(-1_000_000_000, 1, 1_000_000_000, 1, olc::RED);
+        pge.draw_line(-1_000_000_000, -1_000_000_000, 1_000_000_000, 1_000_000_000, olc::GREEN);
+        pge.draw_circle(4, 1_000_006, 1_000_000, olc::YELLOW);
+        pge.draw_string_scaled(-400_000_000, 8, "AA", olc::WHITE, 100_000_000);
+    });
+    let screen = screen.borrow();
+    assert!((0..8).all(|x| x == 1 || screen.get_pixel(x, 1) == olc::RED));
+    assert!((0..8).all(|x| x == 6 || screen.get_pixel(x, x) == olc::GREEN));
+    // the top of a circle that big is flat across the screen
+    assert!((0..8).all(|x| screen.get_pixel(x, 6) == olc::YELLOW));
+    assert_eq!(screen.get_pixel(4, 7), olc::BLUE);
+}
+
+#[test]
+fn huge_fills_are_clipped_before_drawing() {
+    // twice the radius and the distances between the corners don't fit in an i32
+    let screen = draw(8, 8, |pge| {
+        pge.fill_triangle(-2_000_000_000, 7, 2_000_000_000, 7, 0, -2_000_000_000, olc::GREEN);
+        pge.fill_circle(4, 1_100_000_006, 1_100_000_000, olc::YELLOW);
+    });
+    let screen = screen.borrow();
+    assert!((0..8).all(|x| (0..6).all(|y| screen.get_pixel(x, y) == olc::GREEN)));
+    assert!((0..8).all(|x| (6..8).all(|y| screen.get_pixel(x, y) == olc::YELLOW)));
+}
+
+#[test]
+fn clipped_line_patterns_stay_in_step() {
+    let line = |w: usize, x1: i32, x2: i32| {
+        let screen = draw(w, 1, |pge| pge.draw_line_pattern(x1, 0, x2, 0, olc::RED, 0xf0f0_3c3c));
+        let pixels: Vec<Pixel> = (0..w as i32).map(|x| screen.borrow().get_pixel(x, 0)).collect();
+        pixels
+    };
+    // starting 3 pixels off screen picks the pattern up 3 steps in
+    assert_eq!(line(16, -3, 19), line(19, 0, 22)[3..].to_vec());
+    assert_eq!(line(16, -35, 19), line(51, 0, 54)[35..].to_vec());
+}
+
+#[test]
+fn text_skips_bytes_outside_the_font() {
+    let text = |s: &'static str| {
+        let screen = draw(32, 8, |pge| pge.draw_string(0, 0, s, olc::WHITE));
+        let pixels: Vec<Pixel> = (0..8).flat_map(|y| (0..32).map(move |x| (x, y))).map(|(x, y)| screen.borrow().get_pixel(x, y)).collect();
+        pixels
+    };
+    // "é" is two bytes, and each leaves a gap a character wide
+    assert_eq!(text("éA"), text("  A"));
+}
@@ -0,0 +1,552 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use olc_pge as olc;
+use olc::{Key, Pixel, PlatformResult};
+
+// What the engine has done to the window, shared between the test and the platform it owns.
+#[derive(Debug, Default)]
+struct Window {
+    frames_left: u32,
+    title: String,
+    size: (usize, usize),
+    display: (usize, usize),
+    windowed_size: Option<(usize, usize)>,
+    refresh_rate: Option<f32>,
+    mouse_pos: Option<(f32, f32)>,
+    mouse_buttons: [bool; 3],
+    fail_present: bool,
+    presented: Vec<u32>,
+    presented_size: (usize, usize)
+}
+
+#[derive(Debug, Clone)]
+struct MockPlatform(Rc<RefCell<Window>>);
+
+impl MockPlatform {
+    fn new(frames: u32) -> Self {
+        Self(Rc::new(RefCell::new(Window { frames_left: frames, ..Default::default() })))
+    }
+}
+
+impl olc::Platform for MockPlatform {
+    fn is_open(&self) -> bool { self.0.borrow().frames_left > 0 }
+    fn is_focused(&mut self) -> bool { true }
+    fn set_title(&mut self, title: &str) { self.0.borrow_mut().title = title.to_string(); }
+
+    fn get_size(&self) -> (usize, usize) { self.0.borrow().size }
+    fn set_size(&mut self, width: usize, height: usize) -> PlatformResult {
+        self.0.borrow_mut().size = (width, height);
+        Ok(())
+    }
+    fn set_resizable(&mut self, _resizable: bool) -> PlatformResult { Ok(()) }
+
+    // fullscreen fills the display, and going back puts the window back to its old size
+    fn set_fullscreen(&mut self, fullscreen: bool) -> PlatformResult {
+        let mut window = self.0.borrow_mut();
+        if fullscreen && window.windowed_size.is_none() {
+            window.windowed_size = Some(window.size);
+            window.size = window.display;
+        }
+        else if let (false, Some(size)) = (fullscreen, window.windowed_size) {
+            window.size = size;
+            window.windowed_size = None;
+        }
+        Ok(())
+    }
+
+    fn get_refresh_rate(&self) -> Option<f32> { self.0.borrow().refresh_rate }
+
+    fn poll_events(&mut self) {
+        let mut window = self.0.borrow_mut();
+        window.frames_left = window.frames_left.saturating_sub(1);
+    }
+
+    fn present(&mut self, buffer: &[u32], width: usize, height: usize) -> PlatformResult {
+        let mut window = self.0.borrow_mut();
+        if window.fail_present {
+            return Err("lost the window".into())
+        }
+        assert_eq!(buffer.len(), width * height);
+        window.presented = buffer.to_vec();
+        window.presented_size = (width, height);
+        Ok(())
+    }
+
+    fn get_keys(&self) -> Vec<Key> { vec![] }
+    fn get_keys_pressed(&self) -> Vec<Key> { vec![] }
+    fn get_keys_released(&self) -> Vec<Key> { vec![] }
+
+    fn get_mouse_pos(&self) -> Option<(f32, f32)> { self.0.borrow().mouse_pos }
+    fn get_mouse_down(&self, button: usize) -> bool { self.0.borrow().mouse_buttons[button] }
+    fn get_mouse_wheel(&self) -> i32 { 0 }
+}
+
+impl Window {
+    fn pixel(&self, x: usize, y: usize) -> Pixel {
+        Pixel::from(self.presented[y * self.presented_size.0 + x])
+    }
+}
+
+struct Script<F: FnMut(&mut olc::PixelGameEngine, u32) -> bool> {
+    frame: u32,
+    update: F
+}
+
+impl<F: FnMut(&mut olc::PixelGameEngine, u32) -> bool> olc::PGEApplication for Script<F> {
+    const APP_NAME: &'static str = "Platform Test";
+
+    fn on_user_update(&mut self, pge: &mut olc::PixelGameEngine, _elapsed_time: f32) -> bool {
+        let keep_going = (self.update)(pge, self.frame);
+        self.frame += 1;
+        keep_going
+    }
+}
+
+fn script<F: FnMut(&mut olc::PixelGameEngine, u32) -> bool>(update: F) -> Script<F> {
+    Script { frame: 0, update }
+}
+
+#[test]
+fn runs_on_a_custom_platform() {
+    let platform = MockPlatform::new(3);
+    {
+        let mut window = platform.0.borrow_mut();
+        window.mouse_pos = Some((5.0, 3.0));
+        window.mouse_buttons[1] = true;
+    }
+
+    let mut seen = vec![];
+    let app = script(|pge, _| {
+        seen.push((pge.get_mouse_x(), pge.get_mouse_y(), pge.get_mouse(1).held || pge.get_mouse(1).pressed));
+        pge.clear(olc::BLUE);
+        pge.draw(3, 2, olc::RED);
+        true
+    });
+    let mut context = olc::PixelGameEngine::construct_with_platform(app, olc::EngineConfig::new(4, 3, 2, 2), platform.clone());
+    context.start().unwrap();
+    drop(context);
+
+    // the mouse comes in as window pixels, and the frame goes out as screen pixels
+    assert_eq!(seen, vec![(2, 1, true); 3]);
+    let window = platform.0.borrow();
+    assert_eq!(window.presented_size, (4, 3));
+    assert_eq!(window.pixel(0, 0), olc::BLUE);
+    assert_eq!(window.pixel(3, 2), olc::RED);
+}
+
+#[test]
+fn set_screen_size_resizes_the_window() {
+    let platform = MockPlatform::new(2);
+    platform.0.borrow_mut().mouse_pos = Some((15.0, 7.0));
+
+    let mut seen = vec![];
+    let app = script(|pge, frame| {
+        seen.push((pge.screen_width(), pge.screen_height(), pge.get_mouse_x(), pge.get_mouse_y()));
+        if frame == 0 {
+            pge.set_screen_size(8, 4).unwrap();
+        }
+        pge.clear(olc::GREEN);
+        true
+    });
+    let mut context = olc::PixelGameEngine::construct_with_platform(app, olc::EngineConfig::new(4, 3, 2, 2), platform.clone());
+    assert_eq!(platform.0.borrow().size, (8, 6));
+    context.start().unwrap();
+    drop(context);
+
+    // the pixel size stays the same, so the mouse still lands on the same screen pixel
+    assert_eq!(seen, vec![(4, 3, 7, 3), (8, 4, 7, 3)]);
+    let window = platform.0.borrow();
+    assert_eq!(window.size, (16, 8));
+    assert_eq!(window.presented_size, (8, 4));
+    assert_eq!(window.pixel(7, 3), olc::GREEN);
+}
+
+#[test]
+fn set_screen_size_refits_a_resizable_window() {
+    let platform = MockPlatform::new(2);
+    let app = script(|pge, frame| {
+        if frame == 0 {
+            // the window was dragged out wide, so the screen sits 95 pixels in
+            assert_eq!(pge.get_viewport_pos(), olc::Vi2d::new(95, 0));
+            pge.set_screen_size(5, 5).unwrap();
+            assert_eq!((pge.get_viewport_pos(), pge.get_viewport_size()), (olc::Vi2d::new(0, 0), olc::Vi2d::new(5, 5)));
+        }
+        pge.clear(olc::BLUE);
+        pge.draw(4, 4, olc::RED);
+        true
+    });
+    let config = olc::EngineConfig::new(10, 10, 1, 1).resizable(true);
+    let mut context = olc::PixelGameEngine::construct_with_platform(app, config, platform.clone());
+    platform.0.borrow_mut().size = (200, 10);
+    context.start().unwrap();
+    drop(context);
+
+    let window = platform.0.borrow();
+    assert_eq!(window.presented_size, (5, 5));
+    assert_eq!(window.pixel(0, 0), olc::BLUE);
+    assert_eq!(window.pixel(4, 4), olc::RED);
+}
+
+// draws a blue screen with a red bottom right pixel into a resizable window the user has dragged out to 30x13
+fn letterbox(integer_scaling: bool, mouse_pos: (f32, f32)) -> (MockPlatform, olc::Vi2d, olc::Vi2d, (i32, i32)) {
+    let platform = MockPlatform::new(1);
+    let mut viewport = None;
+    let app = script(|pge, _| {
+        viewport = Some((pge.get_viewport_pos(), pge.get_viewport_size(), (pge.get_mouse_x(), pge.get_mouse_y())));
+        pge.clear(olc::BLUE);
+        pge.draw(3, 1, olc::RED);
+        true
+    });
+    let config = olc::EngineConfig::new(4, 2, 1, 1).resizable(true).integer_scaling(integer_scaling);
+    let mut context = olc::PixelGameEngine::construct_with_platform(app, config, platform.clone());
+    {
+        let mut window = platform.0.borrow_mut();
+        window.size = (30, 13);
+        window.mouse_pos = Some(mouse_pos);
+    }
+    context.start().unwrap();
+    drop(context);
+
+    let (pos, size, mouse) = viewport.unwrap();
+    (platform, pos, size, mouse)
+}
+
+#[test]
+fn letterboxes_with_integer_scaling() {
+    let (platform, pos, size, mouse) = letterbox(true, (16.0, 7.0));
+    // 6 window pixels to a screen pixel is as big as fits
+    assert_eq!((pos, size), (olc::Vi2d::new(3, 0), olc::Vi2d::new(24, 12)));
+    assert_eq!(mouse, (2, 1));
+
+    let window = platform.0.borrow();
+    assert_eq!(window.presented_size, (30, 13));
+    assert_eq!(window.pixel(2, 0), olc::BLACK);
+    assert_eq!(window.pixel(3, 0), olc::BLUE);
+    assert_eq!(window.pixel(20, 5), olc::BLUE);
+    assert_eq!(window.pixel(21, 6), olc::RED);
+    assert_eq!(window.pixel(26, 11), olc::RED);
+    assert_eq!(window.pixel(27, 11), olc::BLACK);
+    assert_eq!(window.pixel(26, 12), olc::BLACK);
+}
+
+#[test]
+fn letterboxes_with_fractional_scaling() {
+    let (platform, pos, size, mouse) = letterbox(false, (1.0, 0.0));
+    assert_eq!((pos, size), (olc::Vi2d::new(2, 0), olc::Vi2d::new(26, 13)));
+    // the mouse can be off the screen to the left, in the border
+    assert_eq!(mouse, (-1, 0));
+
+    let window = platform.0.borrow();
+    assert_eq!(window.pixel(1, 0), olc::BLACK);
+    assert_eq!(window.pixel(2, 0), olc::BLUE);
+    assert_eq!(window.pixel(27, 12), olc::RED);
+    assert_eq!(window.pixel(28, 12), olc::BLACK);
+}
+
+#[test]
+fn window_shrinking_during_a_frame() {
+    let platform = MockPlatform::new(1);
+    let app = script(|pge, _| {
+        // the user drags the window smaller while the frame is being drawn
+        platform.0.borrow_mut().size = (10, 5);
+        pge.clear(olc::BLUE);
+        pge.draw(3, 1, olc::RED);
+        true
+    });
+    let config = olc::EngineConfig::new(4, 2, 1, 1).resizable(true);
+    let mut context = olc::PixelGameEngine::construct_with_platform(app, config, platform.clone());
+    platform.0.borrow_mut().size = (30, 13);
+    context.start().unwrap();
+
+    // the frame is fitted to the window it's presented to, not the one it started in
+    assert_eq!(context.engine().get_viewport_pos(), olc::Vi2d::new(1, 0));
+    assert_eq!(context.engine().get_viewport_size(), olc::Vi2d::new(8, 4));
+    drop(context);
+
+    let window = platform.0.borrow();
+    assert_eq!(window.presented_size, (10, 5));
+    assert_eq!(window.pixel(0, 0), olc::BLACK);
+    assert_eq!(window.pixel(1, 0), olc::BLUE);
+    assert_eq!(window.pixel(8, 3), olc::RED);
+    assert_eq!(window.pixel(9, 3), olc::BLACK);
+}
+
+#[test]
+fn fullscreen_toggles_scaling() {
+    let platform = MockPlatform::new(4);
+    platform.0.borrow_mut().display = (50, 20);
+
+    let mut seen = vec![];
+    let app = script(|pge, frame| {
+        seen.push((pge.is_fullscreen(), pge.get_viewport_pos(), pge.get_viewport_size()));
+        if frame == 2 {
+            // the last frame is scaled up to fill the display, keeping its aspect ratio
+            let window = platform.0.borrow();
+            assert_eq!(window.presented_size, (50, 20));
+            assert_eq!(window.pixel(4, 10), olc::BLACK);
+            assert_eq!(window.pixel(5, 10), olc::BLUE);
+            assert_eq!(window.pixel(44, 19), olc::BLUE);
+            assert_eq!(window.pixel(45, 19), olc::BLACK);
+        }
+        if frame == 0 || frame == 2 {
+            pge.set_fullscreen(frame == 0).unwrap();
+        }
+        pge.clear(olc::BLUE);
+        true
+    });
+    let mut context = olc::PixelGameEngine::construct_with_platform(app, olc::EngineConfig::new(4, 2, 2, 2), platform.clone());
+    context.start().unwrap();
+    drop(context);
+
+    assert_eq!(seen, vec![
+        (false, olc::Vi2d::new(0, 0), olc::Vi2d::new(8, 4)),
+        (true, olc::Vi2d::new(5, 0), olc::Vi2d::new(40, 20)),
+        (true, olc::Vi2d::new(5, 0), olc::Vi2d::new(40, 20)),
+        (false, olc::Vi2d::new(0, 0), olc::Vi2d::new(8, 4))
+    ]);
+    let window = platform.0.borrow();
+    assert_eq!(window.size, (8, 4));
+    assert_eq!(window.presented_size, (4, 2));
+}
+
+#[test]
+fn fullscreen_toggles_on_a_resizable_window() {
+    let platform = MockPlatform::new(3);
+    platform.0.borrow_mut().display = (50, 20);
+
+    let app = script(|pge, frame| {
+        // the viewport follows straight away, like an F11 handler would expect
+        match frame {
+            0 => {
+                pge.set_fullscreen(true).unwrap();
+                assert_eq!((pge.get_viewport_pos(), pge.get_viewport_size()), (olc::Vi2d::new(5, 0), olc::Vi2d::new(40, 20)));
+            }
+            1 => {
+                pge.set_fullscreen(false).unwrap();
+                assert_eq!((pge.get_viewport_pos(), pge.get_viewport_size()), (olc::Vi2d::new(3, 0), olc::Vi2d::new(24, 12)));
+            }
+            _ => ()
+        }
+        pge.clear(olc::BLUE);
+        pge.set_sub_pixel_offset(0.5, 0.0);
+        true
+    });
+    let config = olc::EngineConfig::new(4, 2, 1, 1).resizable(true);
+    let mut context = olc::PixelGameEngine::construct_with_platform(app, config, platform.clone());
+    platform.0.borrow_mut().size = (30, 13);
+    context.start().unwrap();
+    drop(context);
+
+    let window = platform.0.borrow();
+    // back in the 24x12 viewport, slid half a screen pixel right
+    assert_eq!(window.presented_size, (30, 13));
+    assert_eq!(window.pixel(5, 0), olc::BLACK);
+    assert_eq!(window.pixel(6, 0), olc::BLUE);
+    assert_eq!(window.pixel(26, 11), olc::BLUE);
+    assert_eq!(window.pixel(27, 11), olc::BLACK);
+}
+
+#[test]
+fn config_sets_up_the_engine() {
+    let platform = MockPlatform::new(1);
+    platform.0.borrow_mut().display = (16, 8);
+
+    let mut seen = None;
+    let app = script(|pge, _| {
+        seen = Some((pge.get_draw_target().borrow().get_pixel(3, 1), matches!(pge.get_pixel_mode(), olc::PixelMode::Alpha),
+            pge.is_fullscreen(), pge.get_viewport_size()));
+        true
+    });
+    let config = olc::EngineConfig::new(4, 2, 1, 1)
+        .title("Config Test")
+        .show_fps(false)
+        .fullscreen(true)
+        .pixel_mode(olc::PixelMode::Alpha)
+        .clear_colour(olc::DARK_BLUE);
+    let mut context = olc::PixelGameEngine::construct_with_platform(app, config, platform.clone());
+    context.start().unwrap();
+    drop(context);
+
+    assert_eq!(seen, Some((olc::DARK_BLUE, true, true, olc::Vi2d::new(16, 8))));
+    let window = platform.0.borrow();
+    assert_eq!(window.title, "Config Test");
+    assert_eq!(window.size, (16, 8));
+}
+
+struct Quitter {
+    create: bool,
+    destroyed: bool
+}
+
+impl olc::PGEApplication for Quitter {
+    const APP_NAME: &'static str = "Quitter";
+
+    fn on_user_create(&mut self, _pge: &mut olc::PixelGameEngine) -> bool { self.create }
+    fn on_user_update(&mut self, _pge: &mut olc::PixelGameEngine, _elapsed_time: f32) -> bool { true }
+    fn on_user_destroy(&mut self) -> bool {
+        self.destroyed = true;
+        true
+    }
+}
+
+#[test]
+fn start_reports_errors() {
+    let app = Quitter { create: false, destroyed: false };
+    let mut context = olc::PixelGameEngine::construct_with_platform(app, olc::EngineConfig::new(4, 2, 1, 1), MockPlatform::new(5));
+    assert!(matches!(context.start(), Err(olc::PgeError::UserAbort)));
+    assert!(!context.app().destroyed);
+
+    // a platform error ends the run, but the app still gets to clean up
+    let platform = MockPlatform::new(5);
+    platform.0.borrow_mut().fail_present = true;
+    let app = Quitter { create: true, destroyed: false };
+    let mut context = olc::PixelGameEngine::construct_with_platform(app, olc::EngineConfig::new(4, 2, 1, 1), platform.clone());
+    match context.start() {
+        Err(olc::PgeError::Backend(e)) => assert_eq!(e.to_string(), "lost the window"),
+        other => panic!("expected a backend error, got {:?}", other)
+    }
+    assert!(context.app().destroyed);
+    assert_eq!(platform.0.borrow().frames_left, 4);
+}
+
+fn run_paced(config: olc::EngineConfig, refresh_rate: Option<f32>) -> (Duration, Vec<f32>) {
+    let platform = MockPlatform::new(5);
+    platform.0.borrow_mut().refresh_rate = refresh_rate;
+
+    let mut context = olc::PixelGameEngine::construct_with_platform(Paced { frame_times: vec![] }, config, platform);
+    let start = Instant::now();
+    context.start().unwrap();
+    (start.elapsed(), context.app().frame_times.clone())
+}
+
+struct Paced {
+    frame_times: Vec<f32>
+}
+
+impl olc::PGEApplication for Paced {
+    const APP_NAME: &'static str = "Paced";
+
+    fn on_user_update(&mut self, _pge: &mut olc::PixelGameEngine, elapsed_time: f32) -> bool {
+        self.frame_times.push(elapsed_time);
+        true
+    }
+}
+
+#[test]
+fn frame_cap_paces_frames() {
+    let (run_time, frame_times) = run_paced(olc::EngineConfig::new(4, 2, 1, 1).frame_cap(Some(100.0)), None);
+    assert!(run_time >= Duration::from_millis(50), "{:?}", run_time);
+    assert!(frame_times[1..].iter().all(|&t| t >= 0.0099), "{:?}", frame_times);
+}
+
+#[test]
+fn vsync_paces_to_the_refresh_rate() {
+    // the slower of the two wins
+    let config = olc::EngineConfig::new(4, 2, 1, 1).frame_cap(Some(100.0)).vsync(true);
+    let (run_time, frame_times) = run_paced(config, Some(50.0));
+    assert!(run_time >= Duration::from_millis(100), "{:?}", run_time);
+    assert!(frame_times[1..].iter().all(|&t| t >= 0.0199), "{:?}", frame_times);
+}
+
+#[test]
+fn decals_are_drawn_over_one_frame() {
+    let mut sprite = olc::Sprite::new(2, 1);
+    sprite.set_pixel(0, 0, olc::RED);
+    sprite.set_pixel(1, 0, olc::GREEN);
+    let decal = olc::Decal::new(sprite.into_ref());
+    let mut white = olc::Sprite::new(1, 1);
+    white.set_pixel(0, 0, olc::WHITE);
+    let white = olc::Decal::new(white.into_ref());
+
+    let platform = MockPlatform::new(2);
+    let app = script(|pge, frame| {
+        pge.clear(olc::BLUE);
+        if frame == 0 {
+            pge.draw_decal(olc::Vf2d::new(1.0, 1.0), &decal, olc::Vf2d::new(2.0, 2.0), olc::WHITE);
+            pge.draw_decal(olc::Vf2d::new(6.0, 0.0), &white, olc::Vf2d::new(1.0, 1.0), Pixel::rgba(255, 255, 0, 255));
+        }
+        else {
+            let window = platform.0.borrow();
+            assert_eq!(window.pixel(1, 1), olc::RED);
+            assert_eq!(window.pixel(2, 2), olc::RED);
+            assert_eq!(window.pixel(3, 1), olc::GREEN);
+            assert_eq!(window.pixel(4, 2), olc::GREEN);
+            assert_eq!(window.pixel(5, 2), olc::BLUE);
+            assert_eq!(window.pixel(1, 3), olc::BLUE);
+            // tinted
+            assert_eq!(window.pixel(6, 0), olc::YELLOW);
+            // only the presented frame had them
+            assert_eq!(pge.get_draw_target().borrow().get_pixel(1, 1), olc::BLUE);
+        }
+        true
+    });
+    let mut context = olc::PixelGameEngine::construct_with_platform(app, olc::EngineConfig::new(8, 4, 1, 1), platform.clone());
+    context.start().unwrap();
+    drop(context);
+
+    assert!(platform.0.borrow().presented.iter().all(|&p| Pixel::from(p) == olc::BLUE));
+}
+
+#[test]
+fn layers_are_stacked_under_the_screen() {
+    let platform = MockPlatform::new(2);
+    let app = script(|pge, frame| {
+        if frame == 0 {
+            let (under, hidden) = (pge.create_layer(), pge.create_layer());
+            pge.enable_layer(under, true);
+            pge.set_layer_offset(under, olc::Vf2d::new(1.0, 0.0));
+            pge.set_draw_target_layer(under);
+            pge.clear(olc::RED);
+            pge.set_draw_target_layer(hidden);
+            pge.clear(olc::GREEN);
+            pge.set_draw_target(None);
+            pge.clear(olc::BLANK);
+            pge.draw(0, 0, olc::BLUE);
+            pge.draw(2, 0, olc::BLUE);
+        }
+        else {
+            // the screen is on top, see-through where nothing was drawn, and the disabled layer isn't drawn at all
+            let window = platform.0.borrow();
+            assert_eq!(window.pixel(0, 0), olc::BLUE);
+            assert_eq!(window.pixel(2, 0), olc::BLUE);
+            assert_eq!(window.pixel(1, 0), olc::RED);
+            assert_eq!(window.pixel(0, 1), olc::BLACK);
+            assert_eq!(window.pixel(1, 1), olc::RED);
+            assert_eq!(window.pixel(5, 1), olc::RED);
+        }
+        true
+    });
+    let mut context = olc::PixelGameEngine::construct_with_platform(app, olc::EngineConfig::new(6, 2, 1, 1), platform.clone());
+    context.start().unwrap();
+    assert_eq!(context.engine().get_layer_count(), 3);
+}
+
+#[test]
+fn sub_pixel_offset_slides_the_frame() {
+    let platform = MockPlatform::new(2);
+    let app = script(|pge, frame| {
+        pge.clear(olc::BLUE);
+        pge.draw(0, 0, olc::RED);
+        pge.draw(2, 1, olc::GREEN);
+        // half a screen pixel right and a quarter up, which is 2 window pixels and 1
+        pge.set_sub_pixel_offset(0.5, -0.25);
+        if frame == 1 {
+            assert_eq!(pge.get_sub_pixel_offset(), olc::Vf2d::new(0.5, -0.25));
+        }
+        true
+    });
+    let mut context = olc::PixelGameEngine::construct_with_platform(app, olc::EngineConfig::new(3, 2, 4, 4), platform.clone());
+    context.start().unwrap();
+    drop(context);
+
+    let window = platform.0.borrow();
+    assert_eq!(window.presented_size, (12, 8));
+    assert_eq!(window.pixel(1, 0), olc::BLACK);
+    assert_eq!(window.pixel(2, 0), olc::RED);
+    assert_eq!(window.pixel(5, 2), olc::RED);
+    assert_eq!(window.pixel(6, 2), olc::BLUE);
+    assert_eq!(window.pixel(11, 6), olc::GREEN);
+    assert_eq!(window.pixel(11, 7), olc::BLACK);
+}
@@ -0,0 +1,172 @@
+use std::fs;
+use std::path::PathBuf;
+
+use image::AnimationDecoder;
+use image::gif::GifDecoder;
+
+use olc_pge as olc;
+use olc::{Pixel, RecordFormat, Recorder, Sprite};
+
+// a fresh directory per test, so tests running side by side don't trip over each other
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("olc-pge-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// fills the screen with a different colour each frame
+struct Colours {
+    frame: u8
+}
+
+impl olc::PGEApplication for Colours {
+    const APP_NAME: &'static str = "Recorder Test";
+
+    fn on_user_update(&mut self, pge: &mut olc::PixelGameEngine, _elapsed_time: f32) -> bool {
+        pge.clear(colour(self.frame));
+        self.frame += 1;
+        true
+    }
+}
+
+fn colour(frame: u8) -> Pixel {
+    [olc::RED, olc::GREEN, olc::BLUE, olc::WHITE, olc::YELLOW, olc::CYAN, olc::MAGENTA][frame as usize % 7]
+}
+
+fn record(frames: u32, recorder: Recorder) {
+    let mut context = olc::PixelGameEngine::construct_headless(Colours { frame: 0 }, 4, 3, Some(frames), vec![]);
+    context.start_recording(recorder).unwrap();
+    assert!(context.is_recording());
+    context.start().unwrap();
+    assert!(!context.is_recording());
+}
+
+#[test]
+fn png_sequence_with_frame_skip() {
+    let dir = temp_dir("record-png");
+    let file = dir.join("clip.png").to_string_lossy().into_owned();
+    record(5, Recorder::new(&file, RecordFormat::PngSequence).frame_skip(1));
+
+    // every other frame is kept, numbered from 0
+    for (i, frame) in [0, 2, 4].iter().enumerate() {
+        let image = Sprite::load_file(&dir.join(format!("clip_{:04}.png", i)).to_string_lossy());
+        assert_eq!((image.width(), image.height()), (4, 3));
+        assert_eq!(image.get_pixel(3, 2), colour(*frame));
+    }
+    assert!(!dir.join("clip_0003.png").exists());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn max_duration_ends_the_recording() {
+    let dir = temp_dir("record-max");
+    let file = dir.join("clip.png").to_string_lossy().into_owned();
+    // frames come at 0, 1/60, 2/60 and 3/60 seconds, and the last is past the limit
+    record(10, Recorder::new(&file, RecordFormat::PngSequence).max_duration(Some(0.04)));
+
+    assert!(dir.join("clip_0002.png").exists());
+    assert!(!dir.join("clip_0003.png").exists());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn gif_frames_and_delays() {
+    let dir = temp_dir("record-gif");
+    let file = dir.join("clip.gif").to_string_lossy().into_owned();
+    record(6, Recorder::new(&file, RecordFormat::Gif));
+
+    let decoder = GifDecoder::new(fs::File::open(&file).unwrap()).unwrap();
+    let frames = decoder.into_frames().collect_frames().unwrap();
+    assert_eq!(frames.len(), 6);
+
+    for (i, frame) in frames.iter().enumerate() {
+        let [r, g, b, a] = frame.buffer().get_pixel(3, 2).0;
+        assert_eq!(Pixel::rgba(r, g, b, a), colour(i as u8));
+    }
+
+    // a 60th of a second doesn't fit in hundredths, so the delays alternate to keep the total right
+    let delays: Vec<u32> = frames.iter().map(|f| { let (n, d) = f.delay().numer_denom_ms(); n / d / 10 }).collect();
+    assert_eq!(delays, vec![2, 1, 2, 2, 1, 2]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// starts recording on frame 1 and stops on frame 3, from inside the app
+struct Toggle {
+    file: String,
+    format: RecordFormat,
+    frame: u8,
+    recording: Vec<bool>
+}
+
+impl olc::PGEApplication for Toggle {
+    const APP_NAME: &'static str = "Recorder Toggle Test";
+
+    fn on_user_update(&mut self, pge: &mut olc::PixelGameEngine, _elapsed_time: f32) -> bool {
+        pge.clear(colour(self.frame));
+        match self.frame {
+            1 => pge.request_recording(Recorder::new(&self.file, self.format)),
+            3 => pge.request_stop_recording(),
+            _ => ()
+        }
+        self.recording.push(pge.is_recording());
+        self.frame += 1;
+        true
+    }
+}
+
+#[test]
+fn app_can_start_and_stop_recording() {
+    let dir = temp_dir("record-toggle");
+    let file = dir.join("clip.png").to_string_lossy().into_owned();
+    let mut context = olc::PixelGameEngine::construct_headless(Toggle { file, format: RecordFormat::PngSequence, frame: 0, recording: vec![] }, 4, 3, Some(6), vec![]);
+    context.start().unwrap();
+    assert_eq!(context.app().recording, vec![false, true, true, false, false, false]);
+
+    // the frame it started on and the frame it stopped on are both in it
+    for (i, frame) in [1, 2, 3].iter().enumerate() {
+        let image = Sprite::load_file(&dir.join(format!("clip_{:04}.png", i)).to_string_lossy());
+        assert_eq!(image.get_pixel(0, 0), colour(*frame));
+    }
+    assert!(!dir.join("clip_0003.png").exists());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn recording_errors_dont_end_the_game() {
+    let dir = temp_dir("record-error");
+    for (name, format) in [("clip.gif", RecordFormat::Gif), ("clip.png", RecordFormat::PngSequence)].iter() {
+        // the gif can't be created, and the first png can't be saved
+        let file = dir.join("missing").join(name).to_string_lossy().into_owned();
+        let app = Toggle { file, format: *format, frame: 0, recording: vec![] };
+        let mut context = olc::PixelGameEngine::construct_headless(app, 4, 3, Some(6), vec![]);
+        context.start().unwrap();
+
+        // every frame still ran, and the recording stopped on the frame it failed
+        assert_eq!(context.app().frame, 6);
+        assert_eq!(context.app().recording, vec![false, true, false, false, false, false]);
+        assert!(matches!(context.engine_mut().take_recording_error(), Some(olc::PgeError::ImageSave(_))));
+        assert!(context.engine_mut().take_recording_error().is_none());
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// /dev/full takes the file being opened but fails every write, so the gif only fails once it's flushed
+#[cfg(target_os = "linux")]
+#[test]
+fn gif_flush_errors_are_reported() {
+    let app = Toggle { file: "/dev/full".to_string(), format: RecordFormat::Gif, frame: 0, recording: vec![] };
+    let mut context = olc::PixelGameEngine::construct_headless(app, 4, 3, Some(6), vec![]);
+    context.start().unwrap();
+
+    assert_eq!(context.app().recording, vec![false, true, true, false, false, false]);
+    match context.engine_mut().take_recording_error() {
+        Some(olc::PgeError::ImageSave(image::ImageError::IoError(e))) => assert_eq!(e.raw_os_error(), Some(28)),
+        other => panic!("expected a write error, got {:?}", other)
+    }
+}
@@ -0,0 +1,120 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use olc_pge as olc;
+
+// a fresh directory per test, so tests running side by side don't trip over each other
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("olc-pge-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write_file(dir: &Path, name: &str, data: &[u8]) -> String {
+    let path = dir.join(name);
+    fs::write(&path, data).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+// Lays a pack out the way olc::ResourcePack::SavePack does: the index length, the index xor'd with
+// key[i % key.size()], then the files in std::map order. Entries must already be sorted.
+fn cpp_pack(entries: &[(&str, &[u8])], key: &str) -> Vec<u8> {
+    let index_len = 4 + entries.iter().map(|(name, _)| 12 + name.len()).sum::<usize>();
+
+    let mut index = vec![];
+    index.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+    let mut offset = 4 + index_len;
+    for (name, data) in entries {
+        index.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        index.extend_from_slice(name.as_bytes());
+        index.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        index.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += data.len();
+    }
+
+    let key = key.as_bytes();
+    let mut pack = vec![];
+    pack.extend_from_slice(&(index_len as u32).to_le_bytes());
+    pack.extend(index.iter().enumerate().map(|(i, b)| if key.is_empty() { *b } else { b ^ key[i % key.len()] }));
+    for (_, data) in entries {
+        pack.extend_from_slice(data);
+    }
+    pack
+}
+
+#[test]
+fn save_and_load_round_trip() {
+    let dir = temp_dir("round-trip");
+    let a = write_file(&dir, "a.txt", b"hello");
+    let b = write_file(&dir, "b.bin", &[0, 1, 2, 255]);
+
+    let mut pack = olc::ResourcePack::new();
+    pack.add_file(&a).unwrap();
+    pack.add_file(&b).unwrap();
+
+    let pack_file = dir.join("test.dat").to_string_lossy().into_owned();
+    pack.save_pack(&pack_file, "secret key").unwrap();
+
+    let loaded = olc::ResourcePack::load_pack(&pack_file, "secret key").unwrap();
+    assert_eq!(loaded.file_names().collect::<Vec<_>>(), vec![a.as_str(), b.as_str()]);
+    assert_eq!(loaded.get_file_buffer(&a), Some(&b"hello"[..]));
+    assert_eq!(loaded.get_file_buffer(&b), Some(&[0, 1, 2, 255][..]));
+    assert!(!loaded.contains("missing.txt"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn wrong_key_is_invalid_data() {
+    let dir = temp_dir("wrong-key");
+    let a = write_file(&dir, "a.txt", b"hello");
+
+    let mut pack = olc::ResourcePack::new();
+    pack.add_file(&a).unwrap();
+    let pack_file = dir.join("test.dat").to_string_lossy().into_owned();
+    pack.save_pack(&pack_file, "right").unwrap();
+
+    match olc::ResourcePack::load_pack(&pack_file, "wrong") {
+        Err(olc::PgeError::ResourcePack(e)) => assert_eq!(e.kind(), ErrorKind::InvalidData),
+        other => panic!("expected a resource pack error, got {:?}", other.map(|_| ()))
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn loads_cpp_layout() {
+    let dir = temp_dir("cpp-load");
+    let pack_file = dir.join("cpp.dat").to_string_lossy().into_owned();
+    fs::write(&pack_file, cpp_pack(&[("gfx/tiles.png", b"tiles"), ("sfx/jump.wav", &[1, 2, 3])], "olc")).unwrap();
+
+    let loaded = olc::ResourcePack::load_pack(&pack_file, "olc").unwrap();
+    assert_eq!(loaded.get_file_buffer("gfx/tiles.png"), Some(&b"tiles"[..]));
+    // windows style separators are looked up the same as posix ones
+    assert_eq!(loaded.get_file_buffer("gfx\\tiles.png"), Some(&b"tiles"[..]));
+    assert_eq!(loaded.file_names().count(), 2);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn saves_cpp_layout() {
+    let dir = temp_dir("cpp-save");
+    let a = write_file(&dir, "a.txt", b"hi");
+    let b = write_file(&dir, "b.bin", &[7, 8, 9]);
+
+    let mut pack = olc::ResourcePack::new();
+    pack.add_file(&b).unwrap();
+    pack.add_file(&a).unwrap();
+
+    let pack_file = dir.join("test.dat").to_string_lossy().into_owned();
+    pack.save_pack(&pack_file, "olc").unwrap();
+    assert_eq!(fs::read(&pack_file).unwrap(), cpp_pack(&[(&a, b"hi"), (&b, &[7, 8, 9])], "olc"));
+
+    pack.save_pack(&pack_file, "").unwrap();
+    assert_eq!(fs::read(&pack_file).unwrap(), cpp_pack(&[(&a, b"hi"), (&b, &[7, 8, 9])], ""));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
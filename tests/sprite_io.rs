@@ -0,0 +1,98 @@
+use std::fs;
+use std::path::PathBuf;
+
+use olc_pge as olc;
+use olc::{Pixel, Sprite};
+
+// a fresh directory per test, so tests running side by side don't trip over each other
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("olc-pge-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// every channel different, and some see-through, so a swapped or dropped channel shows up
+fn test_sprite() -> Sprite {
+    let mut sprite = Sprite::new(3, 2);
+    sprite.set_pixel(0, 0, Pixel::rgba(255, 0, 0, 255));
+    sprite.set_pixel(1, 0, Pixel::rgba(0, 255, 0, 128));
+    sprite.set_pixel(2, 0, Pixel::rgba(0, 0, 255, 0));
+    sprite.set_pixel(0, 1, Pixel::rgba(1, 2, 3, 4));
+    sprite.set_pixel(1, 1, Pixel::rgba(10, 20, 30, 40));
+    sprite.set_pixel(2, 1, Pixel::rgba(200, 150, 100, 50));
+    sprite
+}
+
+fn pixels(sprite: &Sprite) -> Vec<Pixel> {
+    (0..sprite.height() as i32).flat_map(|y| (0..sprite.width() as i32).map(move |x| sprite.get_pixel(x, y))).collect()
+}
+
+#[test]
+fn image_buffer_round_trip() {
+    let sprite = test_sprite();
+    let img = sprite.to_image_buffer();
+    assert_eq!((img.width(), img.height()), (3, 2));
+    assert_eq!(img.get_pixel(1, 0).0, [0, 255, 0, 128]);
+    assert_eq!(img.get_pixel(2, 1).0, [200, 150, 100, 50]);
+
+    assert_eq!(pixels(&Sprite::from_image_buffer(&img)), pixels(&sprite));
+}
+
+#[test]
+fn save_and_load_png() {
+    let dir = temp_dir("sprite-png");
+    let file = dir.join("sprite.png").to_string_lossy().into_owned();
+
+    let sprite = test_sprite();
+    sprite.save_file(&file).unwrap();
+    let loaded = Sprite::load_file(&file);
+    assert_eq!((loaded.width(), loaded.height()), (3, 2));
+    assert_eq!(pixels(&loaded), pixels(&sprite));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn save_reports_errors() {
+    let dir = temp_dir("sprite-save-error");
+
+    // no encoder for the extension
+    let file = dir.join("sprite.nope").to_string_lossy().into_owned();
+    assert!(matches!(test_sprite().save_file(&file), Err(olc::PgeError::ImageSave(_))));
+    // nowhere to put it
+    let file = dir.join("missing").join("sprite.png").to_string_lossy().into_owned();
+    assert!(matches!(test_sprite().save_file(&file), Err(olc::PgeError::ImageSave(_))));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn load_from_memory() {
+    let dir = temp_dir("sprite-memory");
+    let file = dir.join("sprite.png").to_string_lossy().into_owned();
+    test_sprite().save_file(&file).unwrap();
+
+    let loaded = Sprite::from_memory(&fs::read(&file).unwrap()).unwrap();
+    assert_eq!(pixels(&loaded), pixels(&test_sprite()));
+    assert_eq!(pixels(&Sprite::try_load_file(&file).unwrap()), pixels(&test_sprite()));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn load_errors_instead_of_panicking() {
+    assert!(matches!(Sprite::from_memory(b"not an image"), Err(olc::PgeError::ImageLoad(_))));
+    assert!(matches!(Sprite::from_memory(&[]), Err(olc::PgeError::ImageLoad(_))));
+    // a png that stops part way through
+    assert!(matches!(Sprite::from_memory(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Err(olc::PgeError::ImageLoad(_))));
+
+    let dir = temp_dir("sprite-missing");
+    let missing = dir.join("missing.png").to_string_lossy().into_owned();
+    match Sprite::try_load_file(&missing) {
+        Err(olc::PgeError::ImageLoad(image::ImageError::IoError(e))) => assert_eq!(e.kind(), std::io::ErrorKind::NotFound),
+        other => panic!("expected a not found error, got {:?}", other.map(|_| ()))
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}